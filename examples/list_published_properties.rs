@@ -1,10 +1,9 @@
-pub fn main() {
-    let monitor = hsperf::JavaVirtualMachine::list_jvms()
-        .into_iter()
-        .map(|jvm| jvm.monitor())
-        .next()
-        .unwrap()
-        .unwrap();
+pub fn main() -> Result<(), hsperf::Error> {
+    let Some(jvm) = hsperf::JavaVirtualMachine::list_jvms().into_iter().next() else {
+        println!("no jvms!");
+        return Ok(());
+    };
+    let monitor = jvm.monitor()?;
 
     for (entry_name, entry) in monitor.entries().iter() {
         println!(
@@ -13,4 +12,5 @@ pub fn main() {
             entry.unit()
         );
     }
+    Ok(())
 }
@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 
-use hsperf::JvmMonitor;
+use hsperf::{GcStats, JvmMonitor};
 use nix::unistd::sleep;
 
 pub fn main() {
@@ -13,25 +13,10 @@ pub fn main() {
         "sun.rt.applicationTime",
         "sun.gc.cause",
         "sun.gc.lastCause",
-        "sun.gc.collector.0.name",
-        "sun.gc.collector.1.name",
-        "sun.gc.collector.2.name",
         "sun.gc.generation.0.name",
         "sun.gc.generation.0.spaces",
         "sun.gc.generation.1.name",
         "sun.gc.generation.1.spaces",
-        "sun.gc.collector.0.invocations",
-        "sun.gc.collector.0.time",
-        "sun.gc.collector.0.lastEntryTime",
-        "sun.gc.collector.0.lastExitTime",
-        "sun.gc.collector.1.invocations",
-        "sun.gc.collector.1.time",
-        "sun.gc.collector.1.lastEntryTime",
-        "sun.gc.collector.1.lastExitTime",
-        "sun.gc.collector.2.invocations",
-        "sun.gc.collector.2.time",
-        "sun.gc.collector.2.lastEntryTime",
-        "sun.gc.collector.2.lastExitTime",
         "sun.gc.generation.1.capacity",
         "sun.gc.generation.1.space.0.capacity",
         "sun.gc.generation.1.space.0.used",
@@ -62,7 +47,10 @@ pub fn main() {
     let mut monitors: Vec<JvmMonitor> = hsperf::JavaVirtualMachine::list_jvms()
         .into_iter()
         .flat_map(|jvm| jvm.monitor())
-        // .map(|monitor| monitor.only(|s| properies_of_interest.contains(s)))
+        .map(|monitor| {
+            monitor
+                .only(|s| properies_of_interest.contains(s) || s.starts_with("sun.gc.collector."))
+        })
         .collect();
     if monitors.is_empty() {
         println!("no jvms!");
@@ -80,6 +68,21 @@ pub fn main() {
                 for (entry_name, entry) in entries.iter() {
                     println!("\t{}: {:?}", entry_name, entry.value());
                 }
+                if let Ok(gc_stats) = GcStats::from_entries(entries) {
+                    for collector in gc_stats.collectors() {
+                        println!(
+                            "\t{}: {} collections in {:?}{}",
+                            collector.name(),
+                            collector.invocations(),
+                            collector.time(),
+                            if collector.in_progress() {
+                                ", collecting"
+                            } else {
+                                ""
+                            }
+                        );
+                    }
+                }
             }
             sleep(2);
         }
@@ -0,0 +1,16 @@
+#![no_main]
+
+use hsperf::{ParsingMode, Snapshot};
+use libfuzzer_sys::fuzz_target;
+
+// Decodes the bytes as a hsperfdata region in every parsing mode, along with the values
+// of all the entries found.
+fuzz_target!(|data: &[u8]| {
+    for mode in [ParsingMode::Strict, ParsingMode::Lenient] {
+        if let Ok(snapshot) = Snapshot::from_bytes_with_parsing_mode(data, mode) {
+            for entry in snapshot.entries().values() {
+                let _ = entry.value();
+            }
+        }
+    }
+});
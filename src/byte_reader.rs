@@ -0,0 +1,247 @@
+use crate::{PerfDataSource, errors::Error, perf_data::Endianness};
+
+/// Bounds-checked reader of a range of an hsperfdata region, decoding values in the byte order
+/// of the region.
+///
+/// Offsets are relative to the start of the range, which the reader never reads past.
+#[derive(Clone, Copy)]
+pub(crate) struct ByteReader<'a> {
+    region: &'a dyn PerfDataSource,
+    start: usize,
+    length: usize,
+    endianness: Endianness,
+}
+
+impl<'a> ByteReader<'a> {
+    /// Reader of the whole region.
+    pub(crate) fn new(region: &'a dyn PerfDataSource, endianness: Endianness) -> Self {
+        Self {
+            region,
+            start: 0,
+            length: region.len(),
+            endianness,
+        }
+    }
+
+    /// The same range decoded in another byte order.
+    pub(crate) fn with_endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    /// Reader of the length bytes starting at offset.
+    pub(crate) fn slice(&self, offset: usize, length: usize) -> Result<Self, Error> {
+        self.validate_room_for(offset, length)?;
+        Ok(Self {
+            start: self.start + offset,
+            length,
+            ..*self
+        })
+    }
+
+    /// Reader of the bytes from offset to the end of the range.
+    pub(crate) fn skip(&self, offset: usize) -> Result<Self, Error> {
+        let length = self
+            .length
+            .checked_sub(offset)
+            .ok_or(Error::WontBeAbleToRead)?;
+        self.slice(offset, length)
+    }
+
+    pub(crate) fn region(&self) -> &'a dyn PerfDataSource {
+        self.region
+    }
+
+    pub(crate) fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+
+    /// Offset of the range from the start of the region.
+    pub(crate) fn start(&self) -> usize {
+        self.start
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.length
+    }
+
+    pub(crate) fn bytes(&self, offset: usize, count: usize) -> Result<Vec<u8>, Error> {
+        self.validate_room_for(offset, count)?;
+        let mut bytes = vec![0u8; count];
+        self.region.read_at(self.start + offset, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Reads count bytes as a string ending at the first control character.
+    pub(crate) fn string(&self, offset: usize, count: usize) -> Result<String, Error> {
+        Ok(self
+            .bytes(offset, count)?
+            .into_iter()
+            .map(char::from)
+            .take_while(|c| !c.is_ascii_control())
+            .collect())
+    }
+
+    pub(crate) fn u8(&self, offset: usize) -> Result<u8, Error> {
+        self.array::<1>(offset).map(|[byte]| byte)
+    }
+
+    pub(crate) fn u16(&self, offset: usize) -> Result<u16, Error> {
+        self.decode(offset, u16::from_le_bytes, u16::from_be_bytes)
+    }
+
+    pub(crate) fn i16(&self, offset: usize) -> Result<i16, Error> {
+        self.decode(offset, i16::from_le_bytes, i16::from_be_bytes)
+    }
+
+    pub(crate) fn u32(&self, offset: usize) -> Result<u32, Error> {
+        self.decode(offset, u32::from_le_bytes, u32::from_be_bytes)
+    }
+
+    pub(crate) fn i32(&self, offset: usize) -> Result<i32, Error> {
+        self.decode(offset, i32::from_le_bytes, i32::from_be_bytes)
+    }
+
+    pub(crate) fn i64(&self, offset: usize) -> Result<i64, Error> {
+        self.decode(offset, i64::from_le_bytes, i64::from_be_bytes)
+    }
+
+    pub(crate) fn f32(&self, offset: usize) -> Result<f32, Error> {
+        self.decode(offset, f32::from_le_bytes, f32::from_be_bytes)
+    }
+
+    pub(crate) fn f64(&self, offset: usize) -> Result<f64, Error> {
+        self.decode(offset, f64::from_le_bytes, f64::from_be_bytes)
+    }
+
+    fn decode<const N: usize, T>(
+        &self,
+        offset: usize,
+        from_le_bytes: fn([u8; N]) -> T,
+        from_be_bytes: fn([u8; N]) -> T,
+    ) -> Result<T, Error> {
+        let bytes = self.array::<N>(offset)?;
+        Ok(match self.endianness {
+            Endianness::LittleEndian => from_le_bytes(bytes),
+            Endianness::BigEndian => from_be_bytes(bytes),
+        })
+    }
+
+    fn array<const N: usize>(&self, offset: usize) -> Result<[u8; N], Error> {
+        self.validate_room_for(offset, N)?;
+        let mut bytes = [0u8; N];
+        self.region.read_at(self.start + offset, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn validate_room_for(&self, offset: usize, count: usize) -> Result<(), Error> {
+        match offset.checked_add(count) {
+            Some(end) if end <= self.length => Ok(()),
+            _ => Err(Error::WontBeAbleToRead),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parameterized::parameterized;
+    use test_strategy::proptest;
+
+    use crate::{byte_reader::ByteReader, errors::Error, perf_data::Endianness};
+
+    fn given_a_region() -> Vec<u8> {
+        (0..32).collect()
+    }
+
+    #[parameterized(endianness = {
+        Endianness::LittleEndian, Endianness::BigEndian
+    })]
+    fn values_are_decoded_in_the_byte_order(endianness: Endianness) {
+        let mut bytes = vec![0u8; 24];
+        let (short, int, long) = match endianness {
+            Endianness::LittleEndian => (
+                (-2i16).to_le_bytes(),
+                123456i32.to_le_bytes(),
+                1.5f64.to_le_bytes(),
+            ),
+            Endianness::BigEndian => (
+                (-2i16).to_be_bytes(),
+                123456i32.to_be_bytes(),
+                1.5f64.to_be_bytes(),
+            ),
+        };
+        bytes[1..3].copy_from_slice(&short);
+        bytes[4..8].copy_from_slice(&int);
+        bytes[8..16].copy_from_slice(&long);
+        let reader = ByteReader::new(&bytes, endianness);
+
+        assert_eq!(-2, reader.i16(1).unwrap());
+        assert_eq!(123456, reader.i32(4).unwrap());
+        assert_eq!(1.5, reader.f64(8).unwrap());
+    }
+
+    #[test]
+    fn slice_offsets_are_relative_to_its_start() {
+        let region = given_a_region();
+        let reader = ByteReader::new(&region, Endianness::LittleEndian);
+
+        let slice = reader.slice(8, 4).unwrap();
+
+        assert_eq!(8, slice.start());
+        assert_eq!(4, slice.len());
+        assert_eq!(9, slice.u8(1).unwrap());
+    }
+
+    #[test]
+    fn slice_does_not_read_past_its_end() {
+        let region = given_a_region();
+        let slice = ByteReader::new(&region, Endianness::LittleEndian)
+            .slice(8, 4)
+            .unwrap();
+
+        assert!(matches!(slice.i64(0), Err(Error::WontBeAbleToRead)));
+        assert!(matches!(slice.u8(4), Err(Error::WontBeAbleToRead)));
+        assert!(matches!(slice.skip(5), Err(Error::WontBeAbleToRead)));
+    }
+
+    #[test]
+    fn string_ends_at_the_first_control_character() {
+        let region = b"Hello\0World".to_vec();
+        let reader = ByteReader::new(&region, Endianness::LittleEndian);
+
+        assert_eq!("Hello", reader.string(0, 11).unwrap());
+        assert_eq!("World", reader.string(6, 5).unwrap());
+    }
+
+    #[proptest]
+    fn slices_are_only_read_within_the_region(
+        #[strategy(0..64usize)] offset: usize,
+        #[strategy(0..64usize)] length: usize,
+    ) {
+        let region = given_a_region();
+        let reader = ByteReader::new(&region, Endianness::LittleEndian);
+
+        let result = reader.slice(offset, length);
+
+        assert_eq!(offset + length <= 32, result.is_ok());
+        if let Ok(slice) = result {
+            assert_eq!(
+                (offset..offset + length)
+                    .map(|b| b as u8)
+                    .collect::<Vec<u8>>(),
+                slice.bytes(0, length).unwrap()
+            );
+        }
+    }
+
+    #[proptest]
+    fn values_are_only_read_within_the_reader(#[strategy(0..usize::MAX)] offset: usize) {
+        let region = given_a_region();
+        let reader = ByteReader::new(&region, Endianness::LittleEndian);
+
+        assert_eq!(
+            offset.checked_add(8).is_some_and(|end| end <= 32),
+            reader.i64(offset).is_ok()
+        );
+    }
+}
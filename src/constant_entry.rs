@@ -1,14 +1,30 @@
+use std::num::NonZeroU64;
+
 use crate::{EntryValue, perf_data::Unit};
 
 /// Entry read from the Hotspot Data that is characterized as unchanging
+#[derive(Clone)]
 pub struct ConstantEntry {
     value: EntryValue,
     unit: Unit,
+    frequency: Option<NonZeroU64>,
 }
 
 impl ConstantEntry {
     pub(crate) fn new(value: EntryValue, unit: Unit) -> Self {
-        Self { value, unit }
+        Self {
+            value,
+            unit,
+            frequency: None,
+        }
+    }
+
+    pub(crate) fn set_frequency(&mut self, frequency: NonZeroU64) {
+        self.frequency = Some(frequency);
+    }
+
+    pub(crate) fn frequency(&self) -> Option<NonZeroU64> {
+        self.frequency
     }
 
     /// The (constant) value of the entry.
@@ -33,8 +49,8 @@ mod tests {
         let tested_entry = ConstantEntry::new(value, unit);
 
         match tested_entry.value() {
-            EntryValue::Int(1234) => assert!(true),
-            _ => assert!(false),
+            EntryValue::Int(1234) => {}
+            _ => unreachable!(),
         }
     }
 
@@ -1,56 +1,119 @@
-use crate::{EntryValue, errors::Error, perf_data::DataType, safish_pointer::SafishPointer};
-
-pub(crate) enum DataPointer {
-    Boolean(SafishPointer<u8>),
-    Byte(SafishPointer<u8>),
-    Char(SafishPointer<char>),
-    Short(SafishPointer<i16>),
-    Int(SafishPointer<i32>),
-    Long(SafishPointer<i64>),
-    Float(SafishPointer<[u8; 4]>),
-    Double(SafishPointer<[u8; 8]>),
-    BooleanVec(SafishPointer<u8>, usize),
-    ByteVec(SafishPointer<u8>, usize),
-    CharVec(SafishPointer<char>, usize),
-    ShortVec(SafishPointer<i16>, usize),
-    IntVec(SafishPointer<i32>, usize),
-    LongVec(SafishPointer<i64>, usize),
-    FloatVec(SafishPointer<[u8; 4]>, usize),
-    DoubleVec(SafishPointer<[u8; 8]>, usize),
+use crate::{
+    EntryValue, PerfDataSource,
+    byte_reader::ByteReader,
+    errors::Error,
+    perf_data::{DataType, Endianness},
+};
+
+/// Location and type of the value of an entry in an hsperfdata region.
+#[derive(Debug, Clone)]
+pub(crate) struct DataPointer {
+    data_type: DataType,
+    offset: usize,
+    vector_length: Option<usize>,
+    endianness: Endianness,
 }
 
 impl DataPointer {
-    pub(crate) fn new_scalar(data_type: &DataType, ptr: SafishPointer<u8>) -> Result<Self, Error> {
-        Ok(match data_type {
-            DataType::Boolean => Self::Boolean(ptr),
-            DataType::Byte => Self::Byte(ptr),
-            DataType::Char => Self::Char(ptr.convert::<char>()?),
-            DataType::Short => Self::Short(ptr.convert::<i16>()?),
-            DataType::Int => Self::Int(ptr.convert::<i32>()?),
-            DataType::Long => Self::Long(ptr.convert::<i64>()?),
-            DataType::Float => Self::Float(ptr.convert::<[u8; 4]>()?),
-            DataType::Double => Self::Double(ptr.convert::<[u8; 8]>()?),
-        })
+    pub(crate) fn new_scalar(data_type: DataType, offset: usize, endianness: Endianness) -> Self {
+        Self {
+            data_type,
+            offset,
+            vector_length: None,
+            endianness,
+        }
     }
 
     pub(crate) fn new_vector(
-        data_type: &DataType,
+        data_type: DataType,
         length: usize,
-        ptr: SafishPointer<u8>,
-    ) -> Result<Self, Error> {
-        Ok(match data_type {
-            DataType::Boolean => Self::BooleanVec(ptr, length),
-            DataType::Byte => Self::ByteVec(ptr, length),
-            DataType::Char => Self::CharVec(ptr.convert::<char>()?, length),
-            DataType::Short => Self::ShortVec(ptr.convert::<i16>()?, length),
-            DataType::Int => Self::IntVec(ptr.convert::<i32>()?, length),
-            DataType::Long => Self::LongVec(ptr.convert::<i64>()?, length),
-            DataType::Float => Self::FloatVec(ptr.convert::<[u8; 4]>()?, length),
-            DataType::Double => Self::DoubleVec(ptr.convert::<[u8; 8]>()?, length),
-        })
-    }
-
-    pub(crate) fn read_value(&self) -> Result<EntryValue, Error> {
-        EntryValue::new(self)
+        offset: usize,
+        endianness: Endianness,
+    ) -> Self {
+        Self {
+            data_type,
+            offset,
+            vector_length: Some(length),
+            endianness,
+        }
+    }
+
+    /// The same value at another offset, to read it from a copy of its bytes.
+    pub(crate) fn at(&self, offset: usize) -> Self {
+        Self {
+            offset,
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub(crate) fn vector_length(&self) -> Option<usize> {
+        self.vector_length
+    }
+
+    /// Number of bytes of the value.
+    pub(crate) fn size(&self) -> Result<usize, Error> {
+        self.vector_length
+            .unwrap_or(1)
+            .checked_mul(self.data_type.size())
+            .ok_or(Error::WontBeAbleToRead)
+    }
+
+    pub(crate) fn read_value(&self, region: &dyn PerfDataSource) -> Result<EntryValue, Error> {
+        EntryValue::new(&self.data_type, self.vector_length, &self.reader(region)?)
+    }
+
+    /// Copies the bytes of the value, without decoding them.
+    pub(crate) fn read_bytes(&self, region: &dyn PerfDataSource) -> Result<Vec<u8>, Error> {
+        let reader = self.reader(region)?;
+        reader.bytes(0, reader.len())
+    }
+
+    fn reader<'a>(&self, region: &'a dyn PerfDataSource) -> Result<ByteReader<'a>, Error> {
+        ByteReader::new(region, self.endianness).slice(self.offset, self.size()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        EntryValue,
+        data_pointer::DataPointer,
+        errors::Error,
+        perf_data::{DataType, Endianness},
+    };
+
+    #[test]
+    fn read_value_reads_at_the_offset() {
+        let region = vec![0, 0, 0, 0, 0, 42];
+        let data_pointer = DataPointer::new_scalar(DataType::Short, 4, Endianness::BigEndian);
+
+        assert!(matches!(
+            data_pointer.read_value(&region),
+            Ok(EntryValue::Short(42))
+        ));
+    }
+
+    #[test]
+    fn read_value_refuses_vectors_past_the_end_of_the_region() {
+        let region = vec![0; 8];
+        let data_pointer =
+            DataPointer::new_vector(DataType::Int, usize::MAX, 0, Endianness::BigEndian);
+
+        assert!(matches!(
+            data_pointer.read_value(&region),
+            Err(Error::WontBeAbleToRead)
+        ));
+    }
+
+    #[test]
+    fn read_bytes_copies_the_bytes_of_the_value() {
+        let region = vec![1, 2, 3, 4, 5, 6];
+        let data_pointer = DataPointer::new_vector(DataType::Short, 2, 1, Endianness::BigEndian);
+
+        assert_eq!(vec![2, 3, 4, 5], data_pointer.read_bytes(&region).unwrap());
     }
 }
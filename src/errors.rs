@@ -1,12 +1,20 @@
-use std::path::PathBuf;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
-    UnsupportedMajorVersion,
-    UnsupportedMinorVersion,
-    InvalidMagicNumber,
+    UnsupportedMajorVersion(u8),
+    UnsupportedMinorVersion(u8),
+    InvalidMagicNumber(u32),
     FailedToOpen(std::io::Error),
     FailedToReadMetaData(std::io::Error),
+    /// The bytes of an hsperfdata region could not be read from a reader.
+    FailedToRead(std::io::Error),
+    /// The bytes of an hsperfdata region could not be written.
+    FailedToWrite(std::io::Error),
     FailedToMapToMemory(nix::errno::Errno),
     ConversionError(Vec<u8>),
     StringConversionError(std::string::FromUtf8Error),
@@ -16,5 +24,210 @@ pub enum Error {
     WontBeAbleToRead,
     OffsetOutOfBounds,
     NotAlignedForCOnversion,
-    MissingEntry,
+    /// An entry is not published.
+    MissingEntry {
+        name: String,
+    },
+    /// A value kept changing while being read consistently.
+    InconsistentRead,
+    /// A value is not of the type of the entry it is written to, or does not fit in it.
+    ValueDoesNotFit,
+    /// An entry converted into time is not an integer count of ticks.
+    NotInTicks,
+    /// An entry was added to a fake JVM once monitored, which would break its monitors.
+    AlreadyMonitored,
+    /// An entry read by a view does not hold a value of the expected type.
+    UnexpectedValue {
+        name: String,
+    },
+    /// A field of the hsperfdata region holds a value that is not supported or
+    /// does not fit in the region.
+    InvalidField {
+        field: &'static str,
+        value: i64,
+    },
+    /// An entry of the hsperfdata region could not be read.
+    InEntry {
+        /// position of the entry in the region
+        index: usize,
+        /// byte offset of the entry from the start of the region
+        offset: usize,
+        /// name of the entry, when it could be read
+        name: Option<String>,
+        source: Box<Error>,
+    },
+    /// An hsperfdata file or its directory failed the file checks.
+    InsecureFile {
+        path: PathBuf,
+        reason: &'static str,
+    },
+    /// An hsperfdata file could not be read.
+    InFile {
+        path: PathBuf,
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    pub(crate) fn in_entry(self, index: usize, offset: usize, name: Option<String>) -> Self {
+        Error::InEntry {
+            index,
+            offset,
+            name,
+            source: Box::new(self),
+        }
+    }
+
+    pub(crate) fn in_file(self, path: &Path) -> Self {
+        Error::InFile {
+            path: path.to_path_buf(),
+            source: Box::new(self),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnsupportedMajorVersion(version) => {
+                write!(f, "unsupported hsperfdata major version {version}")
+            }
+            Error::UnsupportedMinorVersion(version) => {
+                write!(f, "unsupported hsperfdata minor version {version}")
+            }
+            Error::InvalidMagicNumber(magic) => {
+                write!(f, "invalid magic number {magic:#x} for the byte order")
+            }
+            Error::FailedToOpen(_) => write!(f, "failed to open the hsperfdata file"),
+            Error::FailedToReadMetaData(_) => {
+                write!(f, "failed to read the hsperfdata file metadata")
+            }
+            Error::FailedToRead(_) => write!(f, "failed to read the hsperfdata bytes"),
+            Error::FailedToWrite(_) => write!(f, "failed to write the hsperfdata bytes"),
+            Error::FailedToMapToMemory(_) => {
+                write!(f, "failed to map the hsperfdata file to memory")
+            }
+            Error::ConversionError(bytes) => write!(f, "failed to convert bytes {bytes:?}"),
+            Error::StringConversionError(_) => write!(f, "failed to convert bytes to a string"),
+            Error::FailedToSync(_) => write!(f, "failed to sync memory"),
+            Error::InvalidPath(path) => write!(f, "invalid path {}", path.display()),
+            Error::FailedToParsePid(_) => write!(f, "failed to parse the pid"),
+            Error::WontBeAbleToRead => write!(f, "not enough bytes to read the data"),
+            Error::OffsetOutOfBounds => write!(f, "offset out of bounds"),
+            Error::NotAlignedForCOnversion => write!(f, "data not aligned for its type"),
+            Error::MissingEntry { name } => write!(f, "missing entry {name}"),
+            Error::InconsistentRead => write!(f, "the value kept changing while being read"),
+            Error::ValueDoesNotFit => write!(f, "the value does not fit the entry"),
+            Error::NotInTicks => write!(f, "the entry is not a count of ticks"),
+            Error::AlreadyMonitored => write!(f, "entries cannot be added once monitored"),
+            Error::UnexpectedValue { name } => write!(f, "unexpected value for entry {name}"),
+            Error::InvalidField { field, value } => write!(f, "invalid {field} {value}"),
+            Error::InEntry {
+                index,
+                offset,
+                name: Some(name),
+                ..
+            } => write!(f, "invalid entry {index} ({name}) at offset {offset}"),
+            Error::InEntry {
+                index,
+                offset,
+                name: None,
+                ..
+            } => write!(f, "invalid entry {index} at offset {offset}"),
+            Error::InsecureFile { path, reason } => {
+                write!(f, "refusing insecure {}: {reason}", path.display())
+            }
+            Error::InFile { path, .. } => {
+                write!(f, "failed to read hsperfdata file {}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::FailedToOpen(e)
+            | Error::FailedToReadMetaData(e)
+            | Error::FailedToRead(e)
+            | Error::FailedToWrite(e) => Some(e),
+            Error::FailedToMapToMemory(e) | Error::FailedToSync(e) => Some(e),
+            Error::StringConversionError(e) => Some(e),
+            Error::FailedToParsePid(e) => Some(e),
+            Error::InEntry { source, .. } | Error::InFile { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error as _, path::PathBuf};
+
+    use crate::errors::Error;
+
+    #[test]
+    fn display_includes_the_unsupported_version() {
+        assert_eq!(
+            "unsupported hsperfdata major version 3",
+            Error::UnsupportedMajorVersion(3).to_string()
+        );
+    }
+
+    #[test]
+    fn display_includes_the_entry_context() {
+        let error = Error::WontBeAbleToRead.in_entry(2, 1024, Some("sun.gc.cause".to_string()));
+
+        assert_eq!(
+            "invalid entry 2 (sun.gc.cause) at offset 1024",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn display_includes_the_entry_context_without_name() {
+        let error = Error::WontBeAbleToRead.in_entry(2, 1024, None);
+
+        assert_eq!("invalid entry 2 at offset 1024", error.to_string());
+    }
+
+    #[test]
+    fn source_chains_the_contexts() {
+        let error = Error::OffsetOutOfBounds
+            .in_entry(0, 32, None)
+            .in_file(&PathBuf::from("/tmp/hsperfdata_test/12"));
+
+        assert_eq!(
+            "failed to read hsperfdata file /tmp/hsperfdata_test/12",
+            error.to_string()
+        );
+        let entry_error = error.source().unwrap();
+        assert_eq!("invalid entry 0 at offset 32", entry_error.to_string());
+        let root_error = entry_error.source().unwrap();
+        assert_eq!("offset out of bounds", root_error.to_string());
+        assert!(root_error.source().is_none());
+    }
+
+    #[test]
+    fn display_includes_why_the_file_is_insecure() {
+        let error = Error::InsecureFile {
+            path: PathBuf::from("/tmp/hsperfdata_test"),
+            reason: "is a symlink",
+        };
+
+        assert_eq!(
+            "refusing insecure /tmp/hsperfdata_test: is a symlink",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn source_is_the_io_error() {
+        let error = Error::FailedToOpen(std::io::Error::from(std::io::ErrorKind::NotFound));
+
+        assert_eq!(
+            std::io::Error::from(std::io::ErrorKind::NotFound).to_string(),
+            error.source().unwrap().to_string()
+        );
+    }
 }
@@ -0,0 +1,317 @@
+use std::{
+    fs::{self, File, Metadata, OpenOptions},
+    os::unix::fs::{MetadataExt, OpenOptionsExt},
+    path::Path,
+};
+
+use nix::{libc, unistd::Uid};
+
+use crate::errors::Error;
+
+const WORLD_WRITABLE: u32 = 0o002;
+
+/// How the hsperfdata files and their directory are checked before being read.
+///
+/// Like HotSpot, the checks refuse symlinks, files and directories that are not owned by the
+/// user running the JVM, and world-writable ones, so that another user cannot spoof a JVM.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FileChecks {
+    /// Files failing the checks are refused.
+    #[default]
+    Enforced,
+    /// Any file is read. Meant for the forensic analysis of files copied from other hosts or users.
+    Skipped,
+}
+
+impl FileChecks {
+    /// Opens an hsperfdata file for reading after checking it and its directory.
+    pub(crate) fn open(&self, path: &Path) -> Result<File, Error> {
+        match self {
+            FileChecks::Enforced => {
+                let owner = jvm_owner(path)?;
+                if let Some(directory) = path.parent() {
+                    self.check_directory(directory, owner)?;
+                }
+                // The file itself is checked once opened so that it cannot be swapped in between.
+                let file = OpenOptions::new()
+                    .read(true)
+                    .custom_flags(libc::O_NOFOLLOW)
+                    .open(path)
+                    .map_err(|e| match e.raw_os_error() {
+                        Some(libc::ELOOP) => Error::InsecureFile {
+                            path: path.to_path_buf(),
+                            reason: "is a symlink",
+                        },
+                        _ => Error::FailedToOpen(e),
+                    })?;
+                let metadata = file.metadata().map_err(Error::FailedToReadMetaData)?;
+                check(path, &metadata, false, owner)?;
+                Ok(file)
+            }
+            FileChecks::Skipped => File::open(path).map_err(Error::FailedToOpen),
+        }
+    }
+
+    /// Checks the directory the hsperfdata files of the user with the uid are published in.
+    pub(crate) fn check_directory(&self, path: &Path, owner: u32) -> Result<(), Error> {
+        match self {
+            FileChecks::Enforced => {
+                let metadata = fs::symlink_metadata(path).map_err(Error::FailedToReadMetaData)?;
+                check(path, &metadata, true, owner)
+            }
+            FileChecks::Skipped => Ok(()),
+        }
+    }
+
+    /// Checks an hsperfdata file found while listing its directory, and the directory, without
+    /// following symlinks.
+    pub(crate) fn check_file(&self, path: &Path) -> Result<(), Error> {
+        match self {
+            FileChecks::Enforced => {
+                let owner = jvm_owner(path)?;
+                if let Some(directory) = path.parent() {
+                    self.check_directory(directory, owner)?;
+                }
+                let metadata = fs::symlink_metadata(path).map_err(Error::FailedToReadMetaData)?;
+                check(path, &metadata, false, owner)
+            }
+            FileChecks::Skipped => Ok(()),
+        }
+    }
+}
+
+/// The uid of the user running the JVM that publishes the file: the owner of the process the file
+/// is named after, or the owner of its directory once the process has exited.
+fn jvm_owner(path: &Path) -> Result<u32, Error> {
+    let process = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.parse::<u32>().ok())
+        .and_then(|pid| fs::metadata(format!("/proc/{pid}")).ok());
+    if let Some(process) = process {
+        return Ok(process.uid());
+    }
+    match path.parent() {
+        Some(directory) => fs::symlink_metadata(directory)
+            .map(|metadata| metadata.uid())
+            .map_err(Error::FailedToReadMetaData),
+        None => Ok(Uid::effective().as_raw()),
+    }
+}
+
+fn check(path: &Path, metadata: &Metadata, directory: bool, owner: u32) -> Result<(), Error> {
+    let file_type = metadata.file_type();
+    let reason = if file_type.is_symlink() {
+        Some("is a symlink")
+    } else if directory && !file_type.is_dir() {
+        Some("is not a directory")
+    } else if !directory && !file_type.is_file() {
+        Some("is not a regular file")
+    } else if metadata.uid() != owner {
+        Some("is not owned by the user running the JVM")
+    } else if metadata.mode() & WORLD_WRITABLE != 0 {
+        Some("is world-writable")
+    } else {
+        None
+    };
+    match reason {
+        Some(reason) => Err(Error::InsecureFile {
+            path: path.to_path_buf(),
+            reason,
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env::temp_dir,
+        fs::{self, Permissions},
+        os::unix::{
+            fs::{MetadataExt, PermissionsExt, chown, symlink},
+            process::CommandExt,
+        },
+        path::{Path, PathBuf},
+        process::{Child, Command},
+    };
+
+    use nix::unistd::Uid;
+
+    use crate::{
+        errors::Error,
+        file_checks::{FileChecks, check},
+    };
+
+    fn given_a_directory(name: &str) -> PathBuf {
+        let path = temp_dir().join(format!("hsperf_checks_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir(&path).unwrap();
+        fs::set_permissions(&path, Permissions::from_mode(0o700)).unwrap();
+        path
+    }
+
+    fn given_a_file(directory: &Path) -> PathBuf {
+        let path = directory.join(std::process::id().to_string());
+        fs::write(&path, [0u8; 4]).unwrap();
+        fs::set_permissions(&path, Permissions::from_mode(0o600)).unwrap();
+        path
+    }
+
+    const NOBODY: u32 = 65534;
+
+    /// Runs a process as nobody, which only root can do.
+    fn given_a_process_of_another_user() -> Child {
+        Command::new("sleep")
+            .arg("10")
+            .uid(NOBODY)
+            .gid(NOBODY)
+            .spawn()
+            .unwrap()
+    }
+
+    fn assert_insecure(result: Result<impl Sized, Error>, expected_reason: &str) {
+        assert!(
+            matches!(&result, Err(Error::InsecureFile { reason, .. }) if *reason == expected_reason)
+        );
+    }
+
+    #[test]
+    fn open_accepts_a_private_file() {
+        let directory = given_a_directory("private");
+        let path = given_a_file(&directory);
+
+        let result = FileChecks::Enforced.open(&path);
+
+        fs::remove_dir_all(&directory).unwrap();
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn open_refuses_a_symlinked_file() {
+        let directory = given_a_directory("symlinked_file");
+        let target = given_a_file(&directory);
+        let path = directory.join("5678");
+        symlink(&target, &path).unwrap();
+
+        let result = FileChecks::Enforced.open(&path);
+
+        fs::remove_dir_all(&directory).unwrap();
+        assert_insecure(result, "is a symlink");
+    }
+
+    #[test]
+    fn open_refuses_a_file_in_a_symlinked_directory() {
+        let directory = given_a_directory("symlinked_directory_target");
+        given_a_file(&directory);
+        let link = temp_dir().join(format!("hsperf_checks_link_{}", std::process::id()));
+        let _ = fs::remove_file(&link);
+        symlink(&directory, &link).unwrap();
+
+        let result = FileChecks::Enforced.open(&link.join(std::process::id().to_string()));
+
+        fs::remove_file(&link).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+        assert_insecure(result, "is a symlink");
+    }
+
+    #[test]
+    fn open_refuses_a_world_writable_file() {
+        let directory = given_a_directory("writable_file");
+        let path = given_a_file(&directory);
+        fs::set_permissions(&path, Permissions::from_mode(0o666)).unwrap();
+
+        let result = FileChecks::Enforced.open(&path);
+
+        fs::remove_dir_all(&directory).unwrap();
+        assert_insecure(result, "is world-writable");
+    }
+
+    #[test]
+    fn open_refuses_a_file_in_a_world_writable_directory() {
+        let directory = given_a_directory("writable_directory");
+        let path = given_a_file(&directory);
+        fs::set_permissions(&directory, Permissions::from_mode(0o777)).unwrap();
+
+        let result = FileChecks::Enforced.open(&path);
+
+        fs::remove_dir_all(&directory).unwrap();
+        assert_insecure(result, "is world-writable");
+    }
+
+    #[test]
+    fn open_skips_the_checks_for_forensic_use() {
+        let directory = given_a_directory("skipped");
+        let target = given_a_file(&directory);
+        let path = directory.join("5678");
+        symlink(&target, &path).unwrap();
+        fs::set_permissions(&directory, Permissions::from_mode(0o777)).unwrap();
+
+        let result = FileChecks::Skipped.open(&path);
+
+        fs::remove_dir_all(&directory).unwrap();
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn check_refuses_a_file_owned_by_another_user() {
+        let directory = given_a_directory("owner");
+        let path = given_a_file(&directory);
+        let metadata = fs::metadata(&path).unwrap();
+
+        let result = check(&path, &metadata, false, metadata.uid().wrapping_add(1));
+
+        fs::remove_dir_all(&directory).unwrap();
+        assert_insecure(result, "is not owned by the user running the JVM");
+    }
+
+    #[test]
+    fn check_directory_refuses_a_file() {
+        let directory = given_a_directory("not_directory");
+        let path = given_a_file(&directory);
+
+        let result = FileChecks::Enforced.check_directory(&path, Uid::effective().as_raw());
+
+        fs::remove_dir_all(&directory).unwrap();
+        assert_insecure(result, "is not a directory");
+    }
+
+    #[test]
+    fn open_accepts_the_file_of_a_jvm_run_by_another_user() {
+        if !Uid::effective().is_root() {
+            return;
+        }
+        let mut process = given_a_process_of_another_user();
+        let directory = given_a_directory("other_user");
+        let path = directory.join(process.id().to_string());
+        fs::write(&path, [0u8; 4]).unwrap();
+        chown(&directory, Some(NOBODY), None).unwrap();
+        chown(&path, Some(NOBODY), None).unwrap();
+
+        let result = FileChecks::Enforced.open(&path);
+
+        process.kill().unwrap();
+        process.wait().unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn open_refuses_a_file_planted_for_a_jvm_run_by_another_user() {
+        if !Uid::effective().is_root() {
+            return;
+        }
+        let mut process = given_a_process_of_another_user();
+        let directory = given_a_directory("planted");
+        let path = directory.join(process.id().to_string());
+        fs::write(&path, [0u8; 4]).unwrap();
+        chown(&directory, Some(NOBODY), None).unwrap();
+
+        let result = FileChecks::Enforced.open(&path);
+
+        process.kill().unwrap();
+        process.wait().unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+        assert_insecure(result, "is not owned by the user running the JVM");
+    }
+}
@@ -4,32 +4,80 @@ use std::{
     path::PathBuf,
 };
 
-use crate::{errors::Error, jvm_monitor::JvmMonitor};
+use crate::{
+    errors::Error, file_checks::FileChecks, jvm_monitor::JvmMonitor, parsing::ParsingMode,
+};
 
 #[derive(Debug)]
 pub struct JavaVirtualMachine {
     hs_perf_data_path: PathBuf,
     pid: u32,
+    parsing_mode: ParsingMode,
+    file_checks: FileChecks,
 }
 
 impl JavaVirtualMachine {
     /// Creates a monitor to read the hsperfdata of a specific JVM.
     pub fn monitor(self) -> Result<JvmMonitor, Error> {
-        JvmMonitor::map(self.pid, &self.hs_perf_data_path)
+        JvmMonitor::map(
+            self.pid,
+            &self.hs_perf_data_path,
+            self.parsing_mode,
+            self.file_checks,
+        )
+    }
+
+    /// Refines how the monitor handles the entries it cannot decode. Strict by default.
+    pub fn parsing_mode(mut self, parsing_mode: ParsingMode) -> Self {
+        self.parsing_mode = parsing_mode;
+        self
+    }
+
+    /// Refines how the hsperfdata file is checked before being read. Enforced by default.
+    pub fn file_checks(mut self, file_checks: FileChecks) -> Self {
+        self.file_checks = file_checks;
+        self
     }
 
     pub fn pid(&self) -> u32 {
         self.pid
     }
 
+    /// The Java Virtual Machine that published the hsperfdata file at the path, named after its pid.
+    pub fn from_path(hs_perf_data_path: PathBuf) -> Result<JavaVirtualMachine, Error> {
+        let file_name = hs_perf_data_path
+            .file_name()
+            .ok_or(Error::InvalidPath(hs_perf_data_path.clone()))?;
+        let pid = file_name
+            .to_string_lossy()
+            .parse()
+            .map_err(Error::FailedToParsePid)?;
+        Ok(JavaVirtualMachine {
+            hs_perf_data_path,
+            pid,
+            parsing_mode: ParsingMode::default(),
+            file_checks: FileChecks::default(),
+        })
+    }
+
     /// Lists the Java Virtual Machines on this host that publish their data in /tmp/hsperfdata for the current user.
+    ///
+    /// Symlinks, files not owned by the user running their JVM and world-writable files are left out.
     pub fn list_jvms() -> Vec<JavaVirtualMachine> {
         let mut base_path = temp_dir();
         base_path.push(format!("hsperfdata_{}", whoami::username()));
+        Self::list_jvms_in(base_path, FileChecks::Enforced)
+    }
+
+    /// Lists the Java Virtual Machines that published their data in the directory, checking their
+    /// files as given. The listed JVMs are monitored with the same checks.
+    pub fn list_jvms_in(base_path: PathBuf, file_checks: FileChecks) -> Vec<JavaVirtualMachine> {
         if let Ok(java_processes) = fs::read_dir(base_path) {
             java_processes
-                .flat_map(|b| b)
-                .flat_map(|p| JavaVirtualMachine::try_from(p))
+                .flatten()
+                .filter(|entry| file_checks.check_file(&entry.path()).is_ok())
+                .flat_map(JavaVirtualMachine::try_from)
+                .map(|jvm| jvm.file_checks(file_checks))
                 .collect()
         } else {
             vec![]
@@ -41,17 +89,96 @@ impl TryFrom<DirEntry> for JavaVirtualMachine {
     type Error = Error;
 
     fn try_from(value: DirEntry) -> Result<Self, Self::Error> {
-        let hs_perf_data_path = value.path();
-        let file_name = hs_perf_data_path
-            .file_name()
-            .ok_or(Error::InvalidPath(hs_perf_data_path.clone()))?;
-        let pid = file_name
-            .to_string_lossy()
-            .parse()
-            .map_err(|e| Error::FailedToParsePid(e))?;
-        Ok(JavaVirtualMachine {
-            hs_perf_data_path,
-            pid,
-        })
+        Self::from_path(value.path())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env::temp_dir,
+        fs::{self, Permissions},
+        os::unix::fs::{PermissionsExt, symlink},
+        path::PathBuf,
+    };
+
+    use crate::{Error, FileChecks, JavaVirtualMachine};
+
+    /// Above the largest pid of Linux, so that the files are not checked against running processes.
+    const PID_MAX_LIMIT: u32 = 1 << 22;
+
+    fn given_a_directory(name: &str) -> PathBuf {
+        let path = temp_dir().join(format!("hsperf_jvms_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir(&path).unwrap();
+        fs::set_permissions(&path, Permissions::from_mode(0o755)).unwrap();
+        for pid in [PID_MAX_LIMIT + 12, PID_MAX_LIMIT + 34] {
+            fs::write(path.join(pid.to_string()), [0u8; 4]).unwrap();
+        }
+        path
+    }
+
+    fn pids(jvms: Vec<JavaVirtualMachine>) -> Vec<u32> {
+        let mut pids: Vec<u32> = jvms.iter().map(JavaVirtualMachine::pid).collect();
+        pids.sort();
+        pids
+    }
+
+    #[test]
+    fn from_path_reads_the_pid_from_the_file_name() {
+        let jvm =
+            JavaVirtualMachine::from_path(PathBuf::from("/tmp/hsperfdata_test/1234")).unwrap();
+
+        assert_eq!(1234, jvm.pid());
+    }
+
+    #[test]
+    fn from_path_refuses_file_names_that_are_not_pids() {
+        let result = JavaVirtualMachine::from_path(PathBuf::from("/tmp/hsperfdata_test/jvm"));
+
+        assert!(matches!(result, Err(Error::FailedToParsePid(_))));
+    }
+
+    #[test]
+    fn list_jvms_in_lists_the_files_of_the_directory() {
+        let path = given_a_directory("listed");
+
+        let jvms = JavaVirtualMachine::list_jvms_in(path.clone(), FileChecks::Enforced);
+
+        fs::remove_dir_all(&path).unwrap();
+        assert_eq!(vec![PID_MAX_LIMIT + 12, PID_MAX_LIMIT + 34], pids(jvms));
+    }
+
+    #[test]
+    fn list_jvms_in_leaves_out_symlinks() {
+        let path = given_a_directory("symlinks");
+        symlink(
+            path.join((PID_MAX_LIMIT + 12).to_string()),
+            path.join((PID_MAX_LIMIT + 56).to_string()),
+        )
+        .unwrap();
+
+        let jvms = JavaVirtualMachine::list_jvms_in(path.clone(), FileChecks::Enforced);
+
+        fs::remove_dir_all(&path).unwrap();
+        assert_eq!(vec![PID_MAX_LIMIT + 12, PID_MAX_LIMIT + 34], pids(jvms));
+    }
+
+    #[test]
+    fn list_jvms_in_leaves_out_world_writable_directories() {
+        let path = given_a_directory("writable");
+        fs::set_permissions(&path, Permissions::from_mode(0o777)).unwrap();
+
+        let enforced = JavaVirtualMachine::list_jvms_in(path.clone(), FileChecks::Enforced);
+        let skipped = JavaVirtualMachine::list_jvms_in(path.clone(), FileChecks::Skipped);
+
+        fs::remove_dir_all(&path).unwrap();
+        assert!(enforced.is_empty());
+        assert!(
+            skipped
+                .iter()
+                .all(|jvm| jvm.file_checks == FileChecks::Skipped)
+        );
+        assert_eq!(vec![PID_MAX_LIMIT + 12, PID_MAX_LIMIT + 34], pids(skipped));
     }
 }
@@ -0,0 +1,190 @@
+use std::{
+    collections::HashMap,
+    num::NonZeroU64,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{EntryValue, errors::Error, jvm_monitor::Entry};
+
+pub(crate) const FREQUENCY: &str = "sun.os.hrt.frequency";
+pub(crate) const TICKS: &str = "sun.os.hrt.ticks";
+
+/// Clock of a JVM, converting the ticks of its high-resolution counter into time.
+///
+/// The counter starts when the JVM starts, so tick timestamps such as the ends of the
+/// collections are converted relative to the start of the JVM, using the ticks read when
+/// the clock was created as the reference.
+#[derive(Debug, Clone, Copy)]
+pub struct JvmClock {
+    frequency: NonZeroU64,
+    ticks: i64,
+    instant: Instant,
+    system_time: SystemTime,
+}
+
+impl JvmClock {
+    /// Reads the current ticks of the JVM, to relate them to the time of this process.
+    pub(crate) fn sample(frequency: NonZeroU64, ticks: &Entry) -> Result<Self, Error> {
+        let ticks = match ticks.value()? {
+            EntryValue::Long(ticks) => ticks,
+            _ => return Err(Error::NotInTicks),
+        };
+        Ok(Self {
+            frequency,
+            ticks,
+            instant: Instant::now(),
+            system_time: SystemTime::now(),
+        })
+    }
+
+    /// Number of ticks per second.
+    pub fn frequency(&self) -> u64 {
+        self.frequency.get()
+    }
+
+    /// Converts a number of ticks into a duration.
+    pub fn duration(&self, ticks: i64) -> Result<Duration, Error> {
+        duration_of(ticks, self.frequency)
+    }
+
+    /// Time elapsed since the start of the JVM when the clock was created.
+    pub fn uptime(&self) -> Duration {
+        span(self.ticks.unsigned_abs().into(), self.frequency)
+    }
+
+    /// The instant of a tick timestamp, or None when it cannot be represented.
+    pub fn instant(&self, ticks: i64) -> Option<Instant> {
+        let (later, span) = self.offset(ticks);
+        if later {
+            self.instant.checked_add(span)
+        } else {
+            self.instant.checked_sub(span)
+        }
+    }
+
+    /// The system time of a tick timestamp, or None when it cannot be represented.
+    pub fn system_time(&self, ticks: i64) -> Option<SystemTime> {
+        let (later, span) = self.offset(ticks);
+        if later {
+            self.system_time.checked_add(span)
+        } else {
+            self.system_time.checked_sub(span)
+        }
+    }
+
+    /// The system time the JVM started at.
+    pub fn start_time(&self) -> Option<SystemTime> {
+        self.system_time(0)
+    }
+
+    /// Whether the timestamp is after the reference, and how far from it.
+    fn offset(&self, ticks: i64) -> (bool, Duration) {
+        let delta = i128::from(ticks) - i128::from(self.ticks);
+        (delta >= 0, span(delta.unsigned_abs(), self.frequency))
+    }
+}
+
+/// The frequency published by the JVM, if any.
+pub(crate) fn frequency_of(entries: &HashMap<String, Entry>) -> Option<NonZeroU64> {
+    match entries.get(FREQUENCY)?.value() {
+        Ok(EntryValue::Long(frequency)) => NonZeroU64::new(u64::try_from(frequency).ok()?),
+        _ => None,
+    }
+}
+
+/// Converts a non-negative number of ticks into a duration.
+pub(crate) fn duration_of(ticks: i64, frequency: NonZeroU64) -> Result<Duration, Error> {
+    let ticks = u64::try_from(ticks).map_err(|_| Error::InvalidField {
+        field: "ticks",
+        value: ticks,
+    })?;
+    Ok(span(ticks.into(), frequency))
+}
+
+fn span(ticks: u128, frequency: NonZeroU64) -> Duration {
+    let frequency = u128::from(frequency.get());
+    let seconds = u64::try_from(ticks / frequency).unwrap_or(u64::MAX);
+    // The remainder is less than the frequency, so the nanoseconds are less than a second.
+    let nanos = (ticks % frequency * 1_000_000_000 / frequency) as u32;
+    Duration::new(seconds, nanos)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        num::NonZeroU64,
+        time::{Duration, SystemTime},
+    };
+
+    use parameterized::parameterized;
+
+    use crate::{
+        Error, JvmClock,
+        jvm_clock::{duration_of, span},
+    };
+
+    fn given_a_clock(ticks: i64) -> JvmClock {
+        JvmClock {
+            frequency: NonZeroU64::new(1_000_000).unwrap(),
+            ticks,
+            instant: std::time::Instant::now(),
+            system_time: SystemTime::UNIX_EPOCH + Duration::from_secs(1000),
+        }
+    }
+
+    #[parameterized(
+        ticks = { 0, 1, 999_999_999, 1_000_000_000, 2_500_000_001 },
+        expected = { Duration::ZERO, Duration::from_nanos(1), Duration::from_nanos(999_999_999),
+            Duration::from_secs(1), Duration::new(2, 500_000_001) }
+    )]
+    fn ticks_of_a_nanosecond_clock_are_nanoseconds(ticks: i64, expected: Duration) {
+        assert_eq!(
+            expected,
+            duration_of(ticks, NonZeroU64::new(1_000_000_000).unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn ticks_of_other_frequencies_are_scaled() {
+        let frequency = NonZeroU64::new(3).unwrap();
+
+        assert_eq!(
+            Duration::new(1, 333_333_333),
+            duration_of(4, frequency).unwrap()
+        );
+        assert_eq!(
+            Duration::new(u64::MAX / 3, 0),
+            span(u128::from(u64::MAX), frequency)
+        );
+    }
+
+    #[test]
+    fn negative_ticks_are_refused() {
+        assert!(matches!(
+            given_a_clock(0).duration(-1),
+            Err(Error::InvalidField {
+                field: "ticks",
+                value: -1
+            })
+        ));
+    }
+
+    #[test]
+    fn timestamps_are_relative_to_the_reference() {
+        let clock = given_a_clock(5_000_000);
+
+        assert_eq!(Duration::from_secs(5), clock.uptime());
+        assert_eq!(
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(995)),
+            clock.start_time()
+        );
+        assert_eq!(
+            Some(SystemTime::UNIX_EPOCH + Duration::from_millis(1_000_500)),
+            clock.system_time(5_500_000)
+        );
+        assert_eq!(
+            Duration::from_secs(2),
+            clock.instant(5_000_000).unwrap() - clock.instant(3_000_000).unwrap()
+        );
+    }
+}
@@ -1,23 +1,28 @@
 use std::{
     collections::HashMap,
-    ffi::c_void,
-    fs::File,
-    num::NonZero,
-    os::{fd::AsFd, unix::fs::MetadataExt},
-    path::PathBuf,
-    ptr::NonNull,
+    num::NonZeroU64,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+    time::Duration,
 };
 
-use nix::sys::mman::{MapFlags, ProtFlags, mmap, munmap};
-
 use crate::{
-    EntryValue,
+    EntryValue, JvmClock, PerfDataSource,
     constant_entry::ConstantEntry,
     errors::Error,
+    file_checks::FileChecks,
+    jvm_clock,
+    jvm_status::{FileId, JvmStatus},
+    memory_region::MemoryRegion,
+    parsing::{Diagnostic, ParsingMode},
     perf_data::{PerfDataProlog, Unit},
+    read_mode::ReadMode,
+    snapshot::Snapshot,
     variable_entry::VariableEntry,
 };
 
+#[derive(Clone)]
 pub enum Entry {
     Constant(ConstantEntry),
     Variable(VariableEntry),
@@ -37,12 +42,58 @@ impl Entry {
             Entry::Variable(entry) => entry.unit(),
         }
     }
+
+    /// The value of an entry in ticks, converted with the frequency of the high-resolution
+    /// counter published by its JVM in sun.os.hrt.frequency.
+    pub fn value_as_duration(&self) -> Result<Duration, Error> {
+        if self.unit() != Unit::Ticks {
+            return Err(Error::NotInTicks);
+        }
+        let frequency = self.frequency().ok_or_else(|| Error::MissingEntry {
+            name: jvm_clock::FREQUENCY.to_string(),
+        })?;
+        match self.value()? {
+            EntryValue::Long(ticks) => jvm_clock::duration_of(ticks, frequency),
+            EntryValue::Int(ticks) => jvm_clock::duration_of(ticks.into(), frequency),
+            _ => Err(Error::NotInTicks),
+        }
+    }
+
+    /// Sets the frequency the entry is converted with, when it is in ticks.
+    pub(crate) fn set_frequency(&mut self, frequency: NonZeroU64) {
+        if self.unit() == Unit::Ticks {
+            match self {
+                Entry::Constant(entry) => entry.set_frequency(frequency),
+                Entry::Variable(entry) => entry.set_frequency(frequency),
+            }
+        }
+    }
+
+    fn frequency(&self) -> Option<NonZeroU64> {
+        match self {
+            Entry::Constant(entry) => entry.frequency(),
+            Entry::Variable(entry) => entry.frequency(),
+        }
+    }
 }
+
+/// Monitor of the hsperfdata published by a JVM.
+///
+/// Monitors are cheap to clone and can be shared between threads. The hsperfdata file stays
+/// mapped until the monitor, its clones and all the variable entries read from it are dropped.
+#[derive(Clone)]
 pub struct JvmMonitor {
     pid: u32,
-    prolog_addr: NonNull<c_void>,
-    length: usize,
+    /// The mapped hsperfdata file, for monitors that read one.
+    file: Option<(PathBuf, FileId)>,
+    region: Arc<dyn PerfDataSource>,
     entries: HashMap<String, Entry>,
+    parsing_mode: ParsingMode,
+    diagnostics: Arc<Vec<Diagnostic>>,
+    /// The frequency and current ticks of the high-resolution counter, when published.
+    clock: Option<(NonZeroU64, Entry)>,
+    capture_final_snapshot: bool,
+    final_snapshot: Arc<OnceLock<Snapshot>>,
 }
 
 impl JvmMonitor {
@@ -56,6 +107,28 @@ impl JvmMonitor {
         &self.entries
     }
 
+    /// The entries skipped because they could not be decoded, in lenient parsing mode
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// The clock of the JVM, relating its ticks to the current time.
+    ///
+    /// Fails with `Error::MissingEntry` when the JVM does not publish sun.os.hrt.frequency
+    /// and sun.os.hrt.ticks.
+    pub fn clock(&self) -> Result<JvmClock, Error> {
+        let (frequency, ticks) = self.clock.as_ref().ok_or_else(|| {
+            let name = match jvm_clock::frequency_of(&self.entries) {
+                Some(_) => jvm_clock::TICKS,
+                None => jvm_clock::FREQUENCY,
+            };
+            Error::MissingEntry {
+                name: name.to_string(),
+            }
+        })?;
+        JvmClock::sample(*frequency, ticks)
+    }
+
     /// Refines the JVM monitor so that it only keep the variable entries matching the filter.
     pub fn only<P>(mut self, mut filter: P) -> Self
     where
@@ -65,47 +138,468 @@ impl JvmMonitor {
         self
     }
 
-    pub(crate) fn map(pid: u32, path: &PathBuf) -> Result<Self, Error> {
-        let f = File::open(path).map_err(|e| Error::FailedToOpen(e))?;
-        let length = f
-            .metadata()
-            .map_err(|e| Error::FailedToReadMetaData(e))?
-            .size() as usize;
+    /// Refines how the variable entries read their values while the JVM updates them. Direct by default.
+    pub fn read_mode(mut self, read_mode: ReadMode) -> Self {
+        for entry in self.entries.values_mut() {
+            if let Entry::Variable(entry) = entry {
+                entry.set_read_mode(read_mode);
+            }
+        }
+        self
+    }
+
+    /// Refines the JVM monitor so that it takes a snapshot of the hsperfdata the first time
+    /// status detects that the JVM has exited.
+    pub fn capture_final_snapshot(mut self) -> Self {
+        self.capture_final_snapshot = true;
+        self
+    }
+
+    /// Checks whether the monitored JVM is still running.
+    ///
+    /// When the JVM has exited and the monitor was refined with capture_final_snapshot,
+    /// the last values it published are kept in the final snapshot.
+    pub fn status(&self) -> JvmStatus {
+        let status = match &self.file {
+            Some((path, file_id)) => JvmStatus::check(self.pid, path, *file_id),
+            None => JvmStatus::check_process(self.pid),
+        };
+        if status == JvmStatus::Exited
+            && self.capture_final_snapshot
+            && self.final_snapshot.get().is_none()
+            && let Ok(snapshot) = self.snapshot()
+        {
+            let _ = self.final_snapshot.set(snapshot);
+        }
+        status
+    }
+
+    /// The snapshot taken when the JVM was detected as exited, if any.
+    pub fn final_snapshot(&self) -> Option<&Snapshot> {
+        self.final_snapshot.get()
+    }
+
+    /// Copies the hsperfdata currently published by the JVM into an immutable snapshot.
+    pub fn snapshot(&self) -> Result<Snapshot, Error> {
+        Snapshot::copy(self.pid, self.region.as_ref(), self.parsing_mode)
+    }
+
+    /// Moves the source of the monitor on to its latest bytes. Mapped hsperfdata files
+    /// always show the latest values, so refreshing them does nothing.
+    pub fn refresh(&self) -> Result<(), Error> {
+        self.region.refresh()
+    }
+
+    /// Creates a monitor reading the hsperfdata region of the JVM from the source.
+    ///
+    /// The entries are read from the source once, so its later regions are expected to
+    /// hold the same entries.
+    pub fn from_source<S>(pid: u32, source: S) -> Result<Self, Error>
+    where
+        S: PerfDataSource + 'static,
+    {
+        Self::from_source_with_parsing_mode(pid, source, ParsingMode::Strict)
+    }
+
+    /// Creates a monitor reading the hsperfdata region of the JVM from the source, handling
+    /// the entries it cannot decode according to the parsing mode.
+    pub fn from_source_with_parsing_mode<S>(
+        pid: u32,
+        source: S,
+        parsing_mode: ParsingMode,
+    ) -> Result<Self, Error>
+    where
+        S: PerfDataSource + 'static,
+    {
+        Self::read(pid, None, Arc::new(source), parsing_mode)
+    }
+
+    pub(crate) fn map(
+        pid: u32,
+        path: &Path,
+        parsing_mode: ParsingMode,
+        file_checks: FileChecks,
+    ) -> Result<Self, Error> {
+        Self::map_file(pid, path, parsing_mode, file_checks).map_err(|e| e.in_file(path))
+    }
+
+    fn map_file(
+        pid: u32,
+        path: &Path,
+        parsing_mode: ParsingMode,
+        file_checks: FileChecks,
+    ) -> Result<Self, Error> {
+        let f = file_checks.open(path)?;
+        let metadata = f.metadata().map_err(Error::FailedToReadMetaData)?;
+        let length = usize::try_from(metadata.size()).map_err(|_| Error::WontBeAbleToRead)?;
 
         // Ensure there are enough bytes in the mapped file to read a PerfDataProlog.
         if length < size_of::<PerfDataProlog>() {
             return Err(Error::WontBeAbleToRead);
         }
 
-        let prolog_addr = Self::map_file_to_memory(f, length)?;
+        let region = Arc::new(MemoryRegion::map(f, length)?);
+
+        Self::read(
+            pid,
+            Some((path.to_path_buf(), FileId::of(&metadata))),
+            region,
+            parsing_mode,
+        )
+    }
 
-        let entries = PerfDataProlog::read_entries(&prolog_addr, length)?;
+    fn read(
+        pid: u32,
+        file: Option<(PathBuf, FileId)>,
+        region: Arc<dyn PerfDataSource>,
+        parsing_mode: ParsingMode,
+    ) -> Result<Self, Error> {
+        let (entries, diagnostics) = PerfDataProlog::read_entries(&region, parsing_mode)?;
+        let clock = jvm_clock::frequency_of(&entries).zip(entries.get(jvm_clock::TICKS).cloned());
 
         Ok(Self {
             pid,
-            prolog_addr,
-            length,
+            file,
+            region,
             entries,
+            parsing_mode,
+            diagnostics: Arc::new(diagnostics),
+            clock,
+            capture_final_snapshot: false,
+            final_snapshot: Arc::new(OnceLock::new()),
         })
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, fs, path::PathBuf, time::Duration};
+
+    use proptest::{collection::vec, prelude::any};
+    use test_strategy::proptest;
+
+    use crate::{
+        Endianness, Entry, EntryValue, Error, FileChecks, JavaVirtualMachine, JvmMonitor,
+        JvmStatus, ParsingMode, PerfDataBuilder, ReadMode, ReplaySource, Snapshot, Unit,
+        Variability, jvm_clock, snapshot::tests::given_a_region,
+    };
+
+    /// Goes through the public API the way an agent would, on a file named after a dead pid.
+    fn monitor_everything(name: &str, bytes: &[u8]) -> Vec<Result<JvmMonitor, Error>> {
+        let directory = temp_dir().join(format!("hsperf_monitor_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join(i32::MAX.to_string());
+        fs::write(&path, bytes).unwrap();
+        let results = [ParsingMode::Strict, ParsingMode::Lenient]
+            .into_iter()
+            .map(|parsing_mode| {
+                let monitor = JavaVirtualMachine::from_path(path.clone())?
+                    .parsing_mode(parsing_mode)
+                    .file_checks(FileChecks::Skipped)
+                    .monitor()?
+                    .capture_final_snapshot();
+                for entry in monitor.entries().values() {
+                    let _ = entry.value();
+                }
+                let _ = monitor.status();
+                if let Ok(snapshot) = monitor.snapshot() {
+                    for entry in snapshot.entries().values() {
+                        let _ = entry.value();
+                    }
+                }
+                Ok(monitor)
+            })
+            .collect();
+        fs::remove_dir_all(&directory).unwrap();
+        results
+    }
+
+    fn given_a_file(name: &str) -> PathBuf {
+        let path = temp_dir().join(format!("hsperf_monitor_{}_{}", name, std::process::id()));
+        fs::write(&path, given_a_region(1234)).unwrap();
+        path
+    }
+
+    fn assert_send_and_sync<T: Send + Sync>(_value: Option<T>) {}
+
+    #[test]
+    fn monitor_and_entries_can_be_shared_between_threads() {
+        assert_send_and_sync::<JvmMonitor>(None);
+        assert_send_and_sync::<Snapshot>(None);
+        assert_send_and_sync::<Entry>(None);
+    }
+
+    #[test]
+    fn status_is_running_while_the_process_lives() {
+        let path = given_a_file("running");
+        let monitor = JvmMonitor::map(
+            std::process::id(),
+            &path,
+            ParsingMode::Strict,
+            FileChecks::Skipped,
+        )
+        .unwrap()
+        .capture_final_snapshot();
+
+        let status = monitor.status();
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(JvmStatus::Running, status);
+        assert!(monitor.final_snapshot().is_none());
+    }
+
+    #[test]
+    fn status_captures_a_final_snapshot_when_the_jvm_exited() {
+        let path = given_a_file("exited");
+        let monitor = JvmMonitor::map(
+            i32::MAX as u32,
+            &path,
+            ParsingMode::Strict,
+            FileChecks::Skipped,
+        )
+        .unwrap()
+        .capture_final_snapshot();
+        fs::remove_file(&path).unwrap();
+
+        let status = monitor.status();
+
+        assert_eq!(JvmStatus::Exited, status);
+        let final_snapshot = monitor.final_snapshot().unwrap();
+        assert_eq!(1, final_snapshot.entries().len());
+    }
+
+    #[test]
+    fn read_mode_applies_to_the_variable_entries() {
+        let path = given_a_file("consistent");
+        let monitor = JvmMonitor::map(
+            std::process::id(),
+            &path,
+            ParsingMode::Strict,
+            FileChecks::Skipped,
+        )
+        .unwrap()
+        .read_mode(ReadMode::Consistent);
+
+        let value = monitor.entries()["t.c"].value();
+
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(value, Ok(EntryValue::Long(1234))));
+    }
+
+    #[test]
+    fn status_does_not_capture_a_final_snapshot_unless_asked() {
+        let path = given_a_file("not_captured");
+        let monitor = JvmMonitor::map(
+            i32::MAX as u32,
+            &path,
+            ParsingMode::Strict,
+            FileChecks::Skipped,
+        )
+        .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(JvmStatus::Exited, monitor.status());
+        assert!(monitor.final_snapshot().is_none());
+    }
+
+    fn counter_value(monitor: &JvmMonitor) -> i64 {
+        match monitor.entries()["t.c"].value().unwrap() {
+            EntryValue::Long(value) => value,
+            _ => unreachable!(),
+        }
+    }
 
-    fn map_file_to_memory(f: File, length: usize) -> Result<NonNull<c_void>, Error> {
-        unsafe {
-            mmap(
-                None,
-                NonZero::new(length).unwrap(),
-                ProtFlags::PROT_READ,
-                MapFlags::MAP_SHARED,
-                f.as_fd(),
-                0,
+    #[test]
+    fn from_source_reads_the_entries_of_owned_bytes() {
+        let monitor = JvmMonitor::from_source(12, given_a_region(1234)).unwrap();
+
+        assert_eq!(12, monitor.pid());
+        assert_eq!(1234, counter_value(&monitor));
+        monitor.refresh().unwrap();
+        assert_eq!(1234, counter_value(&monitor));
+    }
+
+    #[test]
+    fn from_source_refuses_malformed_regions() {
+        let result = JvmMonitor::from_source(12, vec![0u8; 64]);
+
+        assert!(matches!(result, Err(Error::InvalidMagicNumber(_))));
+    }
+
+    #[test]
+    fn refresh_moves_the_replay_on_to_the_next_region() {
+        let source = ReplaySource::new(vec![given_a_region(1234), given_a_region(5678)]);
+        let monitor = JvmMonitor::from_source(12, source).unwrap();
+        let entry = monitor.entries()["t.c"].clone();
+
+        monitor.refresh().unwrap();
+
+        assert_eq!(5678, counter_value(&monitor));
+        assert!(matches!(entry.value(), Ok(EntryValue::Long(5678))));
+        assert_eq!(5678, {
+            let snapshot = monitor.snapshot().unwrap();
+            match snapshot.entries()["t.c"].value().unwrap() {
+                EntryValue::Long(value) => value,
+                _ => unreachable!(),
+            }
+        });
+    }
+
+    #[test]
+    fn status_of_a_source_follows_the_process() {
+        let running = JvmMonitor::from_source(std::process::id(), given_a_region(1234)).unwrap();
+        let exited = JvmMonitor::from_source(i32::MAX as u32, given_a_region(1234))
+            .unwrap()
+            .capture_final_snapshot();
+
+        assert_eq!(JvmStatus::Running, running.status());
+        assert_eq!(JvmStatus::Exited, exited.status());
+        assert!(exited.final_snapshot().is_some());
+    }
+
+    fn given_a_clocked_monitor() -> JvmMonitor {
+        let bytes = PerfDataBuilder::new(Endianness::native())
+            .entry(
+                "sun.os.hrt.frequency",
+                EntryValue::Long(1_000_000_000),
+                Unit::Hertz,
+                Variability::Constant,
+            )
+            .entry(
+                "sun.os.hrt.ticks",
+                EntryValue::Long(10_000_000_000),
+                Unit::Ticks,
+                Variability::Variable,
+            )
+            .entry(
+                "sun.gc.collector.0.time",
+                EntryValue::Long(1_500_000_000),
+                Unit::Ticks,
+                Variability::Monotonic,
             )
+            .entry(
+                "sun.gc.collector.0.invocations",
+                EntryValue::Long(3),
+                Unit::Events,
+                Variability::Monotonic,
+            )
+            .build()
+            .unwrap();
+        JvmMonitor::from_source(12, bytes).unwrap()
+    }
+
+    #[test]
+    fn ticks_entries_are_converted_with_the_frequency() {
+        let monitor = given_a_clocked_monitor().only(|name| name.starts_with("sun.gc"));
+
+        assert_eq!(
+            Duration::from_millis(1500),
+            monitor.entries()["sun.gc.collector.0.time"]
+                .value_as_duration()
+                .unwrap()
+        );
+        assert!(matches!(
+            monitor.entries()["sun.gc.collector.0.invocations"].value_as_duration(),
+            Err(Error::NotInTicks)
+        ));
+    }
+
+    #[test]
+    fn clock_relates_the_ticks_to_the_start_of_the_jvm() {
+        let clock = given_a_clocked_monitor().only(|_| false).clock().unwrap();
+
+        assert_eq!(1_000_000_000, clock.frequency());
+        assert_eq!(Duration::from_secs(10), clock.uptime());
+        assert_eq!(
+            Duration::from_millis(8500),
+            clock.instant(10_000_000_000).unwrap() - clock.instant(1_500_000_000).unwrap()
+        );
+    }
+
+    #[test]
+    fn ticks_are_not_converted_without_the_frequency() {
+        let monitor = JvmMonitor::from_source(12, given_a_region(1234)).unwrap();
+
+        assert!(matches!(
+            monitor.clock(),
+            Err(Error::MissingEntry { name }) if name == jvm_clock::FREQUENCY
+        ));
+    }
+
+    #[test]
+    fn map_errors_name_the_file() {
+        let path = temp_dir().join(format!("hsperf_monitor_missing_{}", std::process::id()));
+
+        let error = JvmMonitor::map(
+            std::process::id(),
+            &path,
+            ParsingMode::Strict,
+            FileChecks::Skipped,
+        )
+        .err()
+        .unwrap();
+
+        assert!(matches!(&error, Error::InFile { path: error_path, source }
+            if *error_path == path && matches!(**source, Error::FailedToOpen(_))));
+    }
+
+    #[test]
+    fn map_refuses_files_failing_the_checks() {
+        // The temporary directory is world-writable.
+        let path = given_a_file("insecure");
+
+        let error = JvmMonitor::map(
+            std::process::id(),
+            &path,
+            ParsingMode::Strict,
+            FileChecks::Enforced,
+        )
+        .err()
+        .unwrap();
+
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(&error, Error::InFile { source, .. }
+            if matches!(**source, Error::InsecureFile { reason: "is world-writable", .. })));
+    }
+
+    #[test]
+    fn monitor_refuses_empty_files() {
+        let results = monitor_everything("empty", &[]);
+
+        assert!(
+            results
+                .iter()
+                .all(|result| matches!(result, Err(Error::InFile { .. })))
+        );
+    }
+
+    #[test]
+    fn monitor_refuses_truncated_files_without_panicking() {
+        let bytes = given_a_region(1234);
+
+        for length in 0..bytes.len() {
+            let results = monitor_everything("truncated", &bytes[..length]);
+
+            assert!(results[0].is_err(), "{length}");
         }
-        .map_err(|e| Error::FailedToMapToMemory(e))
     }
-}
 
-impl Drop for JvmMonitor {
-    fn drop(&mut self) {
-        unsafe { munmap(self.prolog_addr, self.length) }.unwrap();
+    #[proptest(cases = 64)]
+    fn monitor_does_not_panic_on_random_files(
+        #[strategy(vec(any::<u8>(), 0..256))] bytes: Vec<u8>,
+    ) {
+        monitor_everything("random", &bytes);
+    }
+
+    #[proptest(cases = 256)]
+    fn monitor_does_not_panic_on_corrupted_files(
+        #[strategy(vec((0..64usize, any::<u8>()), 1..8))] corruptions: Vec<(usize, u8)>,
+    ) {
+        let mut bytes = given_a_region(1234);
+        for (offset, byte) in corruptions {
+            bytes[offset] = byte;
+        }
+
+        monitor_everything("corrupted", &bytes);
     }
 }
@@ -0,0 +1,122 @@
+use std::{fs, os::unix::fs::MetadataExt, path::Path};
+
+use nix::{errno::Errno, sys::signal::kill, unistd::Pid};
+
+/// Liveness of a monitored JVM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JvmStatus {
+    /// The process is alive and its hsperfdata file is the one being monitored.
+    Running,
+    /// The process is gone. The monitored region still holds the last values it published.
+    Exited,
+    /// The process is alive but its hsperfdata file was removed or replaced.
+    /// A JVM removes its file when it shuts down, but so do temporary files cleaners.
+    FileRemoved,
+}
+
+/// Identifies an hsperfdata file so that a file re-created at the same path can be told apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FileId {
+    dev: u64,
+    ino: u64,
+}
+
+impl FileId {
+    pub(crate) fn of(metadata: &fs::Metadata) -> Self {
+        Self {
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+        }
+    }
+}
+
+impl JvmStatus {
+    pub(crate) fn check(pid: u32, path: &Path, file_id: FileId) -> Self {
+        if !Self::is_alive(pid) {
+            JvmStatus::Exited
+        } else if fs::metadata(path)
+            .ok()
+            .map(|metadata| FileId::of(&metadata))
+            != Some(file_id)
+        {
+            JvmStatus::FileRemoved
+        } else {
+            JvmStatus::Running
+        }
+    }
+
+    /// Liveness of a JVM whose hsperfdata does not come from a file.
+    pub(crate) fn check_process(pid: u32) -> Self {
+        if Self::is_alive(pid) {
+            JvmStatus::Running
+        } else {
+            JvmStatus::Exited
+        }
+    }
+
+    pub(crate) fn is_alive(pid: u32) -> bool {
+        match i32::try_from(pid) {
+            // Signal 0 only checks that the process exists. EPERM means it does,
+            // but belongs to another user.
+            Ok(pid) => !matches!(kill(Pid::from_raw(pid), None), Err(Errno::ESRCH)),
+            Err(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, fs, path::PathBuf};
+
+    use crate::jvm_status::{FileId, JvmStatus};
+
+    fn given_a_file(name: &str) -> (PathBuf, FileId) {
+        let path = temp_dir().join(format!("hsperf_status_{}_{}", name, std::process::id()));
+        fs::write(&path, [0u8; 4]).unwrap();
+        let file_id = FileId::of(&fs::metadata(&path).unwrap());
+        (path, file_id)
+    }
+
+    #[test]
+    fn check_is_running_when_process_and_file_exist() {
+        let (path, file_id) = given_a_file("running");
+
+        let status = JvmStatus::check(std::process::id(), &path, file_id);
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(JvmStatus::Running, status);
+    }
+
+    #[test]
+    fn check_is_file_removed_when_file_is_gone() {
+        let (path, file_id) = given_a_file("removed");
+        fs::remove_file(&path).unwrap();
+
+        let status = JvmStatus::check(std::process::id(), &path, file_id);
+
+        assert_eq!(JvmStatus::FileRemoved, status);
+    }
+
+    #[test]
+    fn check_is_file_removed_when_file_is_replaced() {
+        let (path, file_id) = given_a_file("replaced");
+        let (other_path, _) = given_a_file("replacement");
+        fs::rename(&other_path, &path).unwrap();
+
+        let status = JvmStatus::check(std::process::id(), &path, file_id);
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(JvmStatus::FileRemoved, status);
+    }
+
+    #[test]
+    fn check_is_exited_when_process_is_gone() {
+        let (path, file_id) = given_a_file("exited");
+
+        // Beyond the maximum pid allowed by linux.
+        let status = JvmStatus::check(i32::MAX as u32, &path, file_id);
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(JvmStatus::Exited, status);
+    }
+}
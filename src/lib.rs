@@ -1,15 +1,62 @@
+mod byte_reader;
 mod constant_entry;
 mod data_pointer;
 mod entry_value;
 mod errors;
+mod file_checks;
 mod java_virtual_machine;
+mod jvm_clock;
 mod jvm_monitor;
+mod jvm_status;
+mod memory_region;
+mod parsing;
 mod perf_data;
-mod safish_pointer;
+mod perf_data_builder;
+mod perf_data_source;
+mod perf_data_writer;
+mod read_mode;
+mod snapshot;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 mod variable_entry;
+mod views;
 
 pub type EntryValue = entry_value::EntryValue;
+pub type Endianness = perf_data::Endianness;
+pub type Generation = views::Generation;
+pub type HeapLayout = views::HeapLayout;
 pub type JavaVirtualMachine = java_virtual_machine::JavaVirtualMachine;
+pub type JvmClock = jvm_clock::JvmClock;
 pub type JvmMonitor = jvm_monitor::JvmMonitor;
+pub type JvmStatus = jvm_status::JvmStatus;
 pub type Error = errors::Error;
+pub type ClassLoadingStats = views::ClassLoadingStats;
+pub type CollectorStats = views::CollectorStats;
+pub type GcStats = views::GcStats;
+pub type Metaspace = views::Metaspace;
+pub type MetaspacePool = views::MetaspacePool;
+pub type CompileType = views::CompileType;
+pub type CompilerStats = views::CompilerStats;
+pub type CompilerThreadStats = views::CompilerThreadStats;
+pub type FileChecks = file_checks::FileChecks;
 pub type Entry = jvm_monitor::Entry;
+pub type ParsingMode = parsing::ParsingMode;
+pub type PerfDataBuilder = perf_data_builder::PerfDataBuilder;
+pub type PerfDataWriter = perf_data_writer::PerfDataWriter;
+pub type PublishedPerfData = perf_data_writer::PublishedPerfData;
+pub type Counter = perf_data_writer::Counter;
+pub type StringVariable = perf_data_writer::StringVariable;
+pub type Diagnostic = parsing::Diagnostic;
+pub type ReadMode = read_mode::ReadMode;
+pub type ReplaySource = perf_data_source::ReplaySource;
+pub type SafepointInterval = views::SafepointInterval;
+pub type SafepointStats = views::SafepointStats;
+pub type Space = views::Space;
+pub type ThreadStats = views::ThreadStats;
+pub type TlabStats = views::TlabStats;
+pub type StartupProfile = views::StartupProfile;
+pub type Snapshot = snapshot::Snapshot;
+pub type Unit = perf_data::Unit;
+pub type Variability = perf_data::Variability;
+
+pub use perf_data_source::PerfDataSource;
@@ -0,0 +1,255 @@
+//! All the unsafe code of the crate lives in this module.
+//!
+//! A mapped region is written to by the JVM while it is read, so its bytes are never
+//! borrowed: they are only read and written through volatile or atomic accesses of
+//! bounds-checked ranges.
+#[cfg(target_has_atomic = "64")]
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::{ffi::c_void, fs::File, num::NonZero, os::fd::AsFd, ptr::NonNull};
+
+use nix::sys::mman::{MapFlags, ProtFlags, mmap, munmap};
+
+use crate::{PerfDataSource, errors::Error};
+
+/// Read-only mapping of an hsperfdata file, written to by the JVM.
+///
+/// The region is shared through an `Arc` by the monitor and all its variable entries,
+/// so that it stays mapped until the last of them is dropped.
+///
+/// On 32 bits targets, where an atomic 64 bits load may write to the read-only mapping, the
+/// bytes are read one by one, so a counter may be seen half-written by the JVM.
+pub(crate) struct MemoryRegion {
+    addr: NonNull<c_void>,
+    length: usize,
+}
+
+// SAFETY: the region is never written to by this crate, and its memory is only released
+// when the region itself is dropped.
+unsafe impl Send for MemoryRegion {}
+unsafe impl Sync for MemoryRegion {}
+
+impl MemoryRegion {
+    pub(crate) fn map(f: File, length: usize) -> Result<Self, Error> {
+        Self::map_with(f, length, ProtFlags::PROT_READ)
+    }
+
+    fn map_with(f: File, length: usize, protection: ProtFlags) -> Result<Self, Error> {
+        // SAFETY: the mapping is only accessed through the methods below.
+        let addr = unsafe {
+            mmap(
+                None,
+                NonZero::new(length).ok_or(Error::WontBeAbleToRead)?,
+                protection,
+                MapFlags::MAP_SHARED,
+                f.as_fd(),
+                0,
+            )
+        }
+        .map_err(Error::FailedToMapToMemory)?;
+        Ok(Self { addr, length })
+    }
+
+    /// Pointer to the first of length bytes starting at offset, when they lie in the mapping.
+    fn start_of(&self, offset: usize, length: usize) -> Result<*mut u8, Error> {
+        match offset.checked_add(length) {
+            // SAFETY: offset is at most the length of the mapping.
+            Some(end) if end <= self.length => {
+                Ok(unsafe { (self.addr.as_ptr() as *mut u8).add(offset) })
+            }
+            _ => Err(Error::WontBeAbleToRead),
+        }
+    }
+}
+
+/// Read-write mapping of an hsperfdata file published by this process, read by others.
+pub(crate) struct WritableRegion(MemoryRegion);
+
+impl WritableRegion {
+    /// Maps a file opened for reading and writing.
+    pub(crate) fn map(f: File, length: usize) -> Result<Self, Error> {
+        MemoryRegion::map_with(f, length, ProtFlags::PROT_READ | ProtFlags::PROT_WRITE).map(Self)
+    }
+
+    /// Copies the bytes to the mapping starting at offset. Aligned 8 bytes are written in
+    /// a single store where the target has 64 bits atomics, so that a counter is never seen
+    /// half-written.
+    pub(crate) fn write_at(&self, offset: usize, bytes: &[u8]) -> Result<(), Error> {
+        let start = self.0.start_of(offset, bytes.len())?;
+        #[cfg(target_has_atomic = "64")]
+        if bytes.len() == size_of::<u64>() && start.align_offset(align_of::<AtomicU64>()) == 0 {
+            let mut word = [0u8; size_of::<u64>()];
+            word.copy_from_slice(bytes);
+            // SAFETY: the pointer is aligned, in bounds and the mapping is writable.
+            unsafe { AtomicU64::from_ptr(start as *mut u64) }
+                .store(u64::from_ne_bytes(word), Ordering::Release);
+            return Ok(());
+        }
+        for (i, byte) in bytes.iter().enumerate() {
+            // SAFETY: the checked range lies in the writable mapping.
+            unsafe { start.add(i).write_volatile(*byte) };
+        }
+        Ok(())
+    }
+
+    /// Atomically adds to the native i64 at offset, which must be aligned.
+    #[cfg(target_has_atomic = "64")]
+    pub(crate) fn add_i64(&self, offset: usize, delta: i64) -> Result<(), Error> {
+        let start = self.0.start_of(offset, size_of::<i64>())?;
+        if start.align_offset(align_of::<AtomicI64>()) != 0 {
+            return Err(Error::NotAlignedForCOnversion);
+        }
+        // SAFETY: the pointer is aligned, in bounds and the mapping is writable.
+        unsafe { AtomicI64::from_ptr(start as *mut i64) }.fetch_add(delta, Ordering::AcqRel);
+        Ok(())
+    }
+}
+
+impl PerfDataSource for WritableRegion {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn read_at(&self, offset: usize, buffer: &mut [u8]) -> Result<(), Error> {
+        self.0.read_at(offset, buffer)
+    }
+}
+
+impl PerfDataSource for MemoryRegion {
+    fn len(&self) -> usize {
+        self.length
+    }
+
+    /// On 64 bits targets, each aligned 8 bytes are read in a single load, whatever the length
+    /// of the buffer, so that neither a value nor a copy of the region ever holds a counter
+    /// half-written by the JVM.
+    fn read_at(&self, offset: usize, buffer: &mut [u8]) -> Result<(), Error> {
+        let start = self.start_of(offset, buffer.len())? as *const u8;
+        let mut i = 0;
+        while i < buffer.len() {
+            // SAFETY: the checked range lies in the mapping.
+            let at = unsafe { start.add(i) };
+            #[cfg(all(target_pointer_width = "64", target_has_atomic = "64"))]
+            if buffer.len() - i >= size_of::<u64>() && at.align_offset(align_of::<AtomicU64>()) == 0
+            {
+                // SAFETY: the pointer is aligned and in bounds. On 64 bits targets an atomic
+                // load is a plain load, which is fine on the read-only mapping.
+                let word = unsafe { AtomicU64::from_ptr(at as *mut u64) }.load(Ordering::Acquire);
+                buffer[i..i + size_of::<u64>()].copy_from_slice(&word.to_ne_bytes());
+                i += size_of::<u64>();
+                continue;
+            }
+            // SAFETY: the checked range lies in the mapping.
+            buffer[i] = unsafe { at.read_volatile() };
+            i += 1;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for MemoryRegion {
+    fn drop(&mut self) {
+        // Dropping can neither fail nor report a failure, the mapping is leaked instead.
+        // SAFETY: the region owns the mapping, which is not used past this point.
+        let _ = unsafe { munmap(self.addr, self.length) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env::temp_dir,
+        fs::{self, File, OpenOptions},
+        sync::atomic::{AtomicBool, Ordering},
+        thread,
+    };
+
+    use crate::{
+        PerfDataSource,
+        errors::Error,
+        memory_region::{MemoryRegion, WritableRegion},
+    };
+
+    fn given_a_mapping(name: &str, bytes: &[u8]) -> MemoryRegion {
+        let path = temp_dir().join(format!("hsperf_region_{}_{}", name, std::process::id()));
+        fs::write(&path, bytes).unwrap();
+        let region = MemoryRegion::map(File::open(&path).unwrap(), bytes.len());
+        fs::remove_file(&path).unwrap();
+        region.unwrap()
+    }
+
+    #[test]
+    fn map_refuses_empty_files() {
+        let path = temp_dir().join(format!("hsperf_region_empty_{}", std::process::id()));
+        fs::write(&path, []).unwrap();
+
+        let result = MemoryRegion::map(File::open(&path).unwrap(), 0);
+
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(Error::WontBeAbleToRead)));
+    }
+
+    #[test]
+    fn map_reads_the_bytes_of_the_file() {
+        let region = given_a_mapping("mapped", &[1u8, 2, 3]);
+        let mut buffer = [0u8; 3];
+
+        region.read_at(0, &mut buffer).unwrap();
+
+        assert_eq!(3, region.len());
+        assert_eq!([1u8, 2, 3], buffer);
+    }
+
+    #[test]
+    fn read_at_refuses_to_read_past_the_end() {
+        let region = given_a_mapping("bounds", &[1u8, 2, 3, 4, 5]);
+
+        assert!(matches!(
+            region.read_at(3, &mut [0u8; 3]),
+            Err(Error::WontBeAbleToRead)
+        ));
+        assert!(matches!(
+            region.read_at(usize::MAX, &mut [0u8; 2]),
+            Err(Error::WontBeAbleToRead)
+        ));
+    }
+
+    #[test]
+    fn read_at_reads_aligned_and_unaligned_words() {
+        let bytes: Vec<u8> = (0..17).collect();
+        let region = given_a_mapping("words", &bytes);
+        let (mut aligned, mut unaligned) = ([0u8; 8], [0u8; 8]);
+
+        region.read_at(8, &mut aligned).unwrap();
+        region.read_at(9, &mut unaligned).unwrap();
+
+        assert_eq!(bytes[8..16], aligned);
+        assert_eq!(bytes[9..17], unaligned);
+    }
+
+    #[test]
+    #[cfg(all(target_pointer_width = "64", target_has_atomic = "64"))]
+    fn copies_never_hold_half_written_words() {
+        let path = temp_dir().join(format!("hsperf_region_torn_{}", std::process::id()));
+        fs::write(&path, [0u8; 32]).unwrap();
+        let writable = OpenOptions::new().read(true).write(true).open(&path);
+        let writer = WritableRegion::map(writable.unwrap(), 32).unwrap();
+        let reader = MemoryRegion::map(File::open(&path).unwrap(), 32).unwrap();
+        fs::remove_file(&path).unwrap();
+        let done = AtomicBool::new(false);
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                for i in 0..100_000u64 {
+                    let word = if i % 2 == 0 { u64::MAX } else { 0 };
+                    writer.write_at(8, &word.to_ne_bytes()).unwrap();
+                }
+                done.store(true, Ordering::Release);
+            });
+            while !done.load(Ordering::Acquire) {
+                let mut copy = [0u8; 32];
+                reader.read_at(0, &mut copy).unwrap();
+                assert!(copy[8..16] == [0u8; 8] || copy[8..16] == [u8::MAX; 8]);
+            }
+        });
+    }
+}
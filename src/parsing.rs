@@ -0,0 +1,127 @@
+use crate::errors::Error;
+
+/// How the entries of a hsperfdata region that cannot be decoded are handled.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ParsingMode {
+    /// Reading the region fails on the first entry that cannot be decoded.
+    #[default]
+    Strict,
+    /// Entries that cannot be decoded are skipped and reported as diagnostics.
+    Lenient,
+}
+
+/// Entry skipped while reading a hsperfdata region in lenient mode.
+#[derive(Debug)]
+pub struct Diagnostic {
+    index: usize,
+    offset: usize,
+    name: Option<String>,
+    reason: Error,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(index: usize, offset: usize, name: Option<String>, reason: Error) -> Self {
+        Self {
+            index,
+            offset,
+            name,
+            reason,
+        }
+    }
+
+    /// The position of the skipped entry in the region
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The byte offset of the skipped entry from the start of the region
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The name of the skipped entry, when it could be read
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Why the entry could not be decoded
+    pub fn reason(&self) -> &Error {
+        &self.reason
+    }
+}
+
+impl ParsingMode {
+    /// Fails in strict mode, records a diagnostic in lenient mode.
+    pub(crate) fn handle(
+        &self,
+        diagnostics: &mut Vec<Diagnostic>,
+        index: usize,
+        offset: usize,
+        name: Option<String>,
+        reason: Error,
+    ) -> Result<(), Error> {
+        match self {
+            ParsingMode::Strict => Err(reason.in_entry(index, offset, name)),
+            ParsingMode::Lenient => {
+                diagnostics.push(Diagnostic::new(index, offset, name, reason));
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        errors::Error,
+        parsing::{Diagnostic, ParsingMode},
+    };
+
+    #[test]
+    fn default_is_strict() {
+        assert_eq!(ParsingMode::Strict, ParsingMode::default());
+    }
+
+    #[test]
+    fn strict_handle_returns_the_error_in_context() {
+        let mut diagnostics: Vec<Diagnostic> = vec![];
+
+        let result = ParsingMode::Strict.handle(
+            &mut diagnostics,
+            1,
+            64,
+            Some("t.c".to_string()),
+            Error::WontBeAbleToRead,
+        );
+
+        assert!(matches!(
+            result,
+            Err(Error::InEntry {
+                index: 1,
+                offset: 64,
+                ..
+            })
+        ));
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn lenient_handle_records_a_diagnostic() {
+        let mut diagnostics: Vec<Diagnostic> = vec![];
+
+        let result = ParsingMode::Lenient.handle(
+            &mut diagnostics,
+            1,
+            64,
+            Some("t.c".to_string()),
+            Error::WontBeAbleToRead,
+        );
+
+        assert!(result.is_ok());
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(1, diagnostics[0].index());
+        assert_eq!(64, diagnostics[0].offset());
+        assert_eq!(Some("t.c"), diagnostics[0].name());
+        assert!(matches!(diagnostics[0].reason(), Error::WontBeAbleToRead));
+    }
+}
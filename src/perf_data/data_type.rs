@@ -1,13 +1,47 @@
+use crate::errors::Error;
+
 #[repr(u8)]
-#[derive(Debug)]
-#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub(crate) enum DataType {
-    Boolean = 'Z' as u8,
-    Byte = 'B' as u8,
-    Char = 'C' as u8,
-    Short = 'S' as u8,
-    Int = 'I' as u8,
-    Long = 'J' as u8,
-    Float = 'F' as u8,
-    Double = 'D' as u8,
+    Boolean = b'Z',
+    Byte = b'B',
+    Char = b'C',
+    Short = b'S',
+    Int = b'I',
+    Long = b'J',
+    Float = b'F',
+    Double = b'D',
+}
+
+impl DataType {
+    /// Size in bytes of one item of the type.
+    pub(crate) fn size(&self) -> usize {
+        match self {
+            DataType::Boolean | DataType::Byte => 1,
+            DataType::Char | DataType::Short => 2,
+            DataType::Int | DataType::Float => 4,
+            DataType::Long | DataType::Double => 8,
+        }
+    }
+}
+
+impl TryFrom<u8> for DataType {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            b'Z' => Ok(DataType::Boolean),
+            b'B' => Ok(DataType::Byte),
+            b'C' => Ok(DataType::Char),
+            b'S' => Ok(DataType::Short),
+            b'I' => Ok(DataType::Int),
+            b'J' => Ok(DataType::Long),
+            b'F' => Ok(DataType::Float),
+            b'D' => Ok(DataType::Double),
+            _ => Err(Error::InvalidField {
+                field: "data_type",
+                value: value as i64,
+            }),
+        }
+    }
 }
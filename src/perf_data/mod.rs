@@ -3,12 +3,15 @@
 mod data_type;
 mod perf_data_entry_header;
 mod perf_data_prolog;
+mod region_encoder;
 mod unit;
 mod variability;
 
 pub(crate) type PerfDataProlog = perf_data_prolog::PerfDataProlog;
 pub(crate) type PerfDataEntryHeader = perf_data_entry_header::PerfDataEntryHeader;
+pub(crate) type Flag = perf_data_entry_header::Flag;
 pub(crate) type Unit = unit::Unit;
 pub(crate) type DataType = data_type::DataType;
 pub(crate) type Endianness = perf_data_prolog::Endianness;
 pub(crate) type Variability = variability::Variability;
+pub(crate) type RegionEncoder = region_encoder::RegionEncoder;
@@ -1,13 +1,16 @@
+use std::sync::Arc;
+
 use crate::{
+    PerfDataSource,
+    byte_reader::ByteReader,
     constant_entry::ConstantEntry,
     data_pointer::DataPointer,
-    entry_value::EntryValue,
     errors::Error,
     perf_data::{DataType, Unit, Variability},
-    safish_pointer::SafishPointer,
     variable_entry::VariableEntry,
 };
 
+/// ```text
 /// /* The PerfDataEntry structure defines the fixed portion of an entry
 ///  * in the PerfData memory region. The PerfDataBuffer Java libraries
 ///  * are aware of this structure and need to be changed when this
@@ -32,6 +35,7 @@ use crate::{
 ///                                          // data_type is T_ARRAY.
 ///   */
 ///   } PerfDataEntry;
+/// ```
 #[repr(C)]
 #[derive(Debug)]
 pub(crate) struct PerfDataEntryHeader {
@@ -42,10 +46,60 @@ pub(crate) struct PerfDataEntryHeader {
     flags: Flag,
     data_units: Unit,
     data_variability: Variability,
-    data_offset: u8, // offset to data item, relative to start of entry.
+    data_offset: i32, // offset to data item, relative to start of entry.
 }
 
+const HEADER_SIZE: usize = size_of::<PerfDataEntryHeader>();
+
 impl PerfDataEntryHeader {
+    /// Decodes the header at the start of the entry field by field, as a corrupted region
+    /// may hold values that are not valid for the enums it is made of.
+    pub(crate) fn read(entry: &ByteReader) -> Result<Self, Error> {
+        Ok(Self {
+            entry_length: entry.i32(0)?,
+            name_offset: entry.i32(4)?,
+            vector_length: entry.i32(8)?,
+            data_type: DataType::try_from(entry.u8(12)?)?,
+            flags: Flag::try_from(entry.u8(13)?)?,
+            data_units: Unit::try_from(entry.u8(14)?)?,
+            data_variability: Variability::try_from(entry.u8(15)?)?,
+            data_offset: entry.i32(16)?,
+        })
+    }
+
+    /// Verifies that the entry fits in the available bytes, so that the next one can be found.
+    pub(crate) fn validate_length(&self, available: usize) -> Result<(), Error> {
+        if self.entry_length < HEADER_SIZE as i32 || self.entry_length as usize > available {
+            Err(Self::invalid("entry_length", self.entry_length))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Verifies that the name and data of the entry lie within the entry, after its header.
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        let data_length = (self.vector_length.max(1) as usize).checked_mul(self.data_type.size());
+        if self.name_offset < HEADER_SIZE as i32 || self.name_offset >= self.data_offset {
+            Err(Self::invalid("name_offset", self.name_offset))
+        } else if self.vector_length < 0 {
+            Err(Self::invalid("vector_length", self.vector_length))
+        } else if data_length
+            .and_then(|length| length.checked_add(self.data_offset as usize))
+            .is_none_or(|end| end > self.entry_length as usize)
+        {
+            Err(Self::invalid("data_offset", self.data_offset))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn invalid(field: &'static str, value: i32) -> Error {
+        Error::InvalidField {
+            field,
+            value: value as i64,
+        }
+    }
+
     pub(crate) fn is_variable_entry(&self) -> bool {
         self.data_variability == Variability::Monotonic
             || self.data_variability == Variability::Variable
@@ -57,41 +111,65 @@ impl PerfDataEntryHeader {
 
     pub(crate) fn read_variable_entry(
         &self,
-        header_ptr: SafishPointer<PerfDataEntryHeader>,
+        entry: &ByteReader,
+        region: &Arc<dyn PerfDataSource>,
     ) -> Result<(String, VariableEntry), Error> {
-        let entry_ptr = header_ptr.convert()?;
-        let variability = self.data_variability;
-        let unit = self.data_units;
-        let name = self.read_name(entry_ptr.clone().add(self.name_offset as usize)?)?;
-        let data_ptr = entry_ptr.add(self.data_offset as usize)?;
-        let data_pointer = if self.vector_length == 0 {
-            DataPointer::new_scalar(&self.data_type, data_ptr)
-        } else {
-            DataPointer::new_vector(&self.data_type, self.vector_length as usize, data_ptr)
-        }?;
-        Ok((name, VariableEntry::new(data_pointer, variability, unit)))
+        let name = self.read_name(entry)?;
+        let data_pointer = self.data_pointer(entry)?;
+        Ok((
+            name,
+            VariableEntry::new(
+                region.clone(),
+                data_pointer,
+                self.data_variability,
+                self.data_units,
+            ),
+        ))
     }
 
     pub(crate) fn read_constant_entry(
         &self,
-        header_ptr: SafishPointer<PerfDataEntryHeader>,
+        entry: &ByteReader,
     ) -> Result<(String, ConstantEntry), Error> {
-        let entry_ptr = header_ptr.convert()?;
-        let name = self.read_name(entry_ptr.clone().add(self.name_offset as usize)?)?;
-        let unit = self.data_units;
-        let data_ptr = entry_ptr.add(self.data_offset as usize)?;
+        let name = self.read_name(entry)?;
+        let value = self.data_pointer(entry)?.read_value(entry.region())?;
+        Ok((name, ConstantEntry::new(value, self.data_units)))
+    }
+
+    /// Reads the entry name, also used as a best effort to give context to errors.
+    pub(crate) fn read_name(&self, entry: &ByteReader) -> Result<String, Error> {
+        let name_offset = usize::try_from(self.name_offset)
+            .map_err(|_| Self::invalid("name_offset", self.name_offset))?;
+        let length = self
+            .data_offset
+            .checked_sub(self.name_offset)
+            .and_then(|length| usize::try_from(length).ok())
+            .ok_or(Self::invalid("name_offset", self.name_offset))?;
+        entry.string(name_offset, length)
+    }
+
+    /// Locates the data of the entry, once checked that it lies within the entry.
+    fn data_pointer(&self, entry: &ByteReader) -> Result<DataPointer, Error> {
+        let data_offset = usize::try_from(self.data_offset)
+            .map_err(|_| Self::invalid("data_offset", self.data_offset))?;
         let data_pointer = if self.vector_length == 0 {
-            DataPointer::new_scalar(&self.data_type, data_ptr)?
+            DataPointer::new_scalar(
+                self.data_type,
+                entry.start() + data_offset,
+                entry.endianness(),
+            )
         } else {
-            DataPointer::new_vector(&self.data_type, self.vector_length as usize, data_ptr)?
+            let vector_length = usize::try_from(self.vector_length)
+                .map_err(|_| Self::invalid("vector_length", self.vector_length))?;
+            DataPointer::new_vector(
+                self.data_type,
+                vector_length,
+                entry.start() + data_offset,
+                entry.endianness(),
+            )
         };
-        let value = EntryValue::new(&data_pointer)?;
-        let reference = ConstantEntry::new(value, unit);
-        Ok((name, reference))
-    }
-
-    fn read_name(&self, ptr: SafishPointer<u8>) -> Result<String, Error> {
-        ptr.read_string(self.data_offset as usize - self.name_offset as usize)
+        entry.slice(data_offset, data_pointer.size()?)?;
+        Ok(data_pointer)
     }
 }
 
@@ -102,28 +180,58 @@ impl PerfDataEntryHeader {
 //   };
 #[repr(u8)]
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
-enum Flag {
+pub(crate) enum Flag {
     None = 0,
     Supported = 1,
 }
 
+impl Flag {
+    /// The flags of the entry named so, supported in the java and com.sun namespaces as in
+    /// PerfDataManager.
+    pub(crate) fn of(name: &str) -> Self {
+        if name.starts_with("java.") || name.starts_with("com.sun.") {
+            Flag::Supported
+        } else {
+            Flag::None
+        }
+    }
+}
+
+impl TryFrom<u8> for Flag {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Flag::None),
+            1 => Ok(Flag::Supported),
+            _ => Err(Error::InvalidField {
+                field: "flags",
+                value: value as i64,
+            }),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use parameterized::parameterized;
     use test_strategy::proptest;
 
     use crate::{
-        EntryValue,
+        EntryValue, PerfDataBuilder, PerfDataSource,
+        byte_reader::ByteReader,
         errors::Error,
         perf_data::{
             DataType, Endianness, Variability,
             perf_data_entry_header::{Flag, PerfDataEntryHeader},
             unit::Unit,
         },
-        safish_pointer::SafishPointer,
     };
 
+    const PROLOG_SIZE: usize = 32;
+
     fn given_a_header() -> PerfDataEntryHeader {
         PerfDataEntryHeader {
             entry_length: 0,
@@ -133,21 +241,56 @@ mod tests {
             flags: Flag::None,
             data_units: Unit::Hertz,
             data_variability: Variability::Invalid,
-            data_offset: 0 as u8,
+            data_offset: 0,
         }
     }
 
-    fn pack_binary_value(name: &str, value: u8) -> Vec<u8> {
-        let mut binary_data: Vec<u8> = vec![0; (name.len() + 1) as usize];
-        binary_data[..name.len()].copy_from_slice(name.as_bytes());
-        binary_data[name.len()] = value;
-        binary_data
+    fn given_a_valid_header() -> PerfDataEntryHeader {
+        let mut header = given_a_header();
+        header.entry_length = 32;
+        header.name_offset = 20;
+        header.data_offset = 24;
+        header.data_type = DataType::Long;
+        header
+    }
+
+    fn given_a_region(binary_data: &[u8]) -> Arc<dyn PerfDataSource> {
+        Arc::new(binary_data.to_vec())
+    }
+
+    /// Lays out a region holding the single entry, in ticks.
+    fn given_a_built_region(
+        name: &str,
+        value: EntryValue,
+        variability: Variability,
+    ) -> Arc<dyn PerfDataSource> {
+        let builder = PerfDataBuilder::new(Endianness::BigEndian);
+        Arc::new(
+            builder
+                .entry(name, value, Unit::Ticks, variability)
+                .build()
+                .unwrap(),
+        )
+    }
+
+    /// Reads the header of the entry following the prolog, the way the prolog does.
+    fn read_the_entry(region: &Arc<dyn PerfDataSource>) -> (ByteReader<'_>, PerfDataEntryHeader) {
+        let reader = ByteReader::new(region.as_ref(), Endianness::BigEndian);
+        let entry = reader.skip(PROLOG_SIZE).unwrap();
+        let header = PerfDataEntryHeader::read(&entry).unwrap();
+        header.validate_length(entry.len()).unwrap();
+        header.validate().unwrap();
+        (
+            entry.slice(0, header.entry_length() as usize).unwrap(),
+            header,
+        )
     }
 
-    fn pack_binary_array(name: &str, value: &str) -> Vec<u8> {
-        let mut binary_data: Vec<u8> = vec![0; (name.len() + value.len()) as usize];
+    /// Packs a name followed by a byte, without the header locating them.
+    fn pack_binary_value(name: &str, value: u8) -> Vec<u8> {
+        let mut binary_data: Vec<u8> = vec![0; name.len() + 1];
         binary_data[..name.len()].copy_from_slice(name.as_bytes());
-        binary_data[name.len()..].copy_from_slice(value.as_bytes());
+        binary_data[name.len()] = value;
         binary_data
     }
 
@@ -180,84 +323,40 @@ mod tests {
     }
 
     #[proptest]
-    fn name_is_read_from_the_pointer(
-        #[strategy("[a-zA-Z.]{1, 10}")] name: String,
-        #[strategy(0..100)] name_offset: i32,
-    ) {
-        let mut tested_header = given_a_header();
-        tested_header.name_offset = name_offset;
-        tested_header.data_offset = name_offset as u8 + name.len() as u8;
-
-        let safish_pointer =
-            SafishPointer::new(name.as_ptr(), name.len(), Endianness::BigEndian).unwrap();
+    fn name_is_read_from_the_entry(#[strategy("[a-zA-Z.]{1, 10}")] name: String) {
+        let region = given_a_built_region(&name, EntryValue::Long(0), Variability::Constant);
+        let (entry, header) = read_the_entry(&region);
 
-        assert_eq!(name, tested_header.read_name(safish_pointer).unwrap());
+        assert_eq!(name, header.read_name(&entry).unwrap());
     }
 
     #[test]
     fn read_constant_entry_populates_a_constant_scalar_entry() {
-        let name = "test.vm";
-        let value = 123;
-        let unit = Unit::Ticks;
-        let mut tested_header = given_a_header();
-        tested_header.name_offset = 0;
-        tested_header.data_offset = name.len() as u8;
-        tested_header.data_type = DataType::Byte;
-        tested_header.vector_length = 0;
-        tested_header.data_units = unit;
-
-        let binary_data = pack_binary_value(&name, value);
-
-        // Fake the pointer
-        let safish_pointer = SafishPointer::new(
-            binary_data.as_ptr(),
-            size_of::<PerfDataEntryHeader>() + name.len() + 1,
-            Endianness::BigEndian,
-        )
-        .unwrap();
+        let region = given_a_built_region("test.vm", EntryValue::Byte(123), Variability::Constant);
+        let (entry, header) = read_the_entry(&region);
 
-        let (entry_name, constant_entry) = tested_header
-            .read_constant_entry(safish_pointer.convert().unwrap())
-            .unwrap();
+        let (entry_name, constant_entry) = header.read_constant_entry(&entry).unwrap();
 
-        assert_eq!(name, entry_name);
+        assert_eq!("test.vm", entry_name);
         match constant_entry.value() {
-            EntryValue::Byte(content) => assert_eq!(content, value),
-            _ => assert!(false),
+            EntryValue::Byte(content) => assert_eq!(content, 123),
+            _ => unreachable!(),
         }
         assert!(matches!(constant_entry.unit(), Unit::Ticks));
     }
 
     #[test]
     fn read_constant_entry_populates_a_constant_vector_entry() {
-        let name = "test.vm";
-        let value = "12";
-        let unit = Unit::Ticks;
-        let mut tested_header = given_a_header();
-        tested_header.name_offset = 0;
-        tested_header.data_offset = name.len() as u8;
-        tested_header.data_type = DataType::Byte;
-        tested_header.vector_length = 2;
-        tested_header.data_units = unit;
+        let value = EntryValue::String("12".to_string());
+        let region = given_a_built_region("test.vm", value, Variability::Constant);
+        let (entry, header) = read_the_entry(&region);
 
-        let binary_data = pack_binary_array(&name, &value);
+        let (entry_name, constant_entry) = header.read_constant_entry(&entry).unwrap();
 
-        // Fake the pointer
-        let safish_pointer = SafishPointer::new(
-            binary_data.as_ptr(),
-            size_of::<PerfDataEntryHeader>() + name.len() + 1,
-            Endianness::BigEndian,
-        )
-        .unwrap();
-
-        let (entry_name, constant_entry) = tested_header
-            .read_constant_entry(safish_pointer.convert().unwrap())
-            .unwrap();
-
-        assert_eq!(name, entry_name);
+        assert_eq!("test.vm", entry_name);
         match constant_entry.value() {
-            EntryValue::String(content) => assert_eq!(*content, value.to_string()),
-            _ => assert!(false),
+            EntryValue::String(content) => assert_eq!(*content, "12".to_string()),
+            _ => unreachable!(),
         }
         assert!(matches!(constant_entry.unit(), Unit::Ticks));
     }
@@ -269,23 +368,17 @@ mod tests {
         let unit = Unit::Ticks;
         let mut tested_header = given_a_header();
         tested_header.name_offset = size_of::<PerfDataEntryHeader>() as i32;
-        tested_header.data_offset = (size_of::<PerfDataEntryHeader>() + name.len()) as u8;
+        tested_header.data_offset = (size_of::<PerfDataEntryHeader>() + name.len()) as i32;
         tested_header.data_type = DataType::Byte;
         tested_header.vector_length = 0;
         tested_header.data_units = unit;
 
-        let binary_data = pack_binary_value(&name, value);
+        let binary_data = pack_binary_value(name, value);
 
-        // Fake the pointer
-        let safish_pointer = SafishPointer::new(
-            binary_data.as_ptr(),
-            size_of::<PerfDataEntryHeader>(),
-            Endianness::BigEndian,
-        )
-        .unwrap();
+        let region = given_a_region(&binary_data);
 
         let error = tested_header
-            .read_constant_entry(safish_pointer.convert().unwrap())
+            .read_constant_entry(&ByteReader::new(region.as_ref(), Endianness::BigEndian))
             .err()
             .unwrap();
 
@@ -294,75 +387,138 @@ mod tests {
 
     #[test]
     fn read_variable_entry_populates_a_variable_scalar_data_reference() {
-        let name = "test.vm";
-        let value = 123;
-        let unit = Unit::Ticks;
-        let variability = Variability::Monotonic;
-        let mut tested_header = given_a_header();
-        tested_header.name_offset = 0;
-        tested_header.data_offset = name.len() as u8;
-        tested_header.data_type = DataType::Byte;
-        tested_header.vector_length = 0;
-        tested_header.data_units = unit;
-        tested_header.data_variability = variability;
-
-        let binary_data = pack_binary_value(&name, value);
+        let region = given_a_built_region("test.vm", EntryValue::Byte(123), Variability::Monotonic);
+        let (entry, header) = read_the_entry(&region);
 
-        // Fake the pointer
-        let safish_pointer = SafishPointer::new(
-            binary_data.as_ptr(),
-            size_of::<PerfDataEntryHeader>() + name.len() + 1,
-            Endianness::BigEndian,
-        )
-        .unwrap();
+        let (entry_name, entry) = header.read_variable_entry(&entry, &region).unwrap();
 
-        let (entry_name, entry) = tested_header
-            .read_variable_entry(safish_pointer.convert().unwrap())
-            .unwrap();
-
-        assert_eq!(name, entry_name);
+        assert_eq!("test.vm", entry_name);
         assert!(matches!(entry.unit(), Unit::Ticks));
         assert!(matches!(entry.variability(), Variability::Monotonic));
         match entry.value().unwrap() {
-            EntryValue::Byte(content) => assert_eq!(content, value),
-            _ => assert!(false),
+            EntryValue::Byte(content) => assert_eq!(content, 123),
+            _ => unreachable!(),
         }
     }
 
     #[test]
     fn read_variable_entry_populates_a_variable_vector_data_reference() {
-        let name = "test.vm";
-        let value = "12";
-        let unit = Unit::Ticks;
-        let variability = Variability::Monotonic;
-        let mut tested_header = given_a_header();
-        tested_header.name_offset = 0;
-        tested_header.data_offset = name.len() as u8;
-        tested_header.data_type = DataType::Byte;
-        tested_header.vector_length = 2;
-        tested_header.data_units = unit;
-        tested_header.data_variability = variability;
+        let value = EntryValue::String("12".to_string());
+        let region = given_a_built_region("test.vm", value, Variability::Monotonic);
+        let (entry, header) = read_the_entry(&region);
 
-        let binary_data = pack_binary_array(&name, &value);
+        let (entry_name, entry) = header.read_variable_entry(&entry, &region).unwrap();
 
-        // Fake the pointer
-        let safish_pointer = SafishPointer::new(
-            binary_data.as_ptr(),
-            size_of::<PerfDataEntryHeader>() + name.len() + 1,
-            Endianness::BigEndian,
-        )
-        .unwrap();
-
-        let (entry_name, entry) = tested_header
-            .read_variable_entry(safish_pointer.convert().unwrap())
-            .unwrap();
-
-        assert_eq!(name, entry_name);
+        assert_eq!("test.vm", entry_name);
         match entry.value().unwrap() {
-            EntryValue::String(content) => assert_eq!(content, value.to_string()),
-            _ => assert!(false),
+            EntryValue::String(content) => assert_eq!(content, "12".to_string()),
+            _ => unreachable!(),
         }
         assert!(matches!(entry.unit(), Unit::Ticks));
         assert!(matches!(entry.variability(), Variability::Monotonic));
     }
+
+    #[test]
+    fn validate_length_accepts_an_entry_fitting_in_the_region() {
+        assert!(given_a_valid_header().validate_length(32).is_ok());
+    }
+
+    #[parameterized(entry_length = {
+        -32, 0, 19, 33
+    })]
+    fn validate_length_rejects_entries_not_fitting_in_the_region(entry_length: i32) {
+        let mut tested_header = given_a_valid_header();
+        tested_header.entry_length = entry_length;
+
+        assert!(matches!(
+            tested_header.validate_length(32),
+            Err(Error::InvalidField {
+                field: "entry_length",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn validate_accepts_a_well_formed_entry() {
+        assert!(given_a_valid_header().validate().is_ok());
+    }
+
+    #[parameterized(name_offset = {
+        -1, 0, 19, 24, 31
+    })]
+    fn validate_rejects_names_outside_the_entry(name_offset: i32) {
+        let mut tested_header = given_a_valid_header();
+        tested_header.name_offset = name_offset;
+
+        assert!(matches!(
+            tested_header.validate(),
+            Err(Error::InvalidField {
+                field: "name_offset",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn validate_rejects_negative_vector_length() {
+        let mut tested_header = given_a_valid_header();
+        tested_header.vector_length = -1;
+
+        assert!(matches!(
+            tested_header.validate(),
+            Err(Error::InvalidField {
+                field: "vector_length",
+                ..
+            })
+        ));
+    }
+
+    #[parameterized(data_offset = {
+        25, 32, i32::MAX
+    })]
+    fn validate_rejects_data_past_the_end_of_the_entry(data_offset: i32) {
+        let mut tested_header = given_a_valid_header();
+        tested_header.data_offset = data_offset;
+
+        assert!(matches!(
+            tested_header.validate(),
+            Err(Error::InvalidField {
+                field: "data_offset",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn validate_rejects_vectors_past_the_end_of_the_entry() {
+        let mut tested_header = given_a_valid_header();
+        tested_header.vector_length = i32::MAX;
+
+        assert!(matches!(
+            tested_header.validate(),
+            Err(Error::InvalidField {
+                field: "data_offset",
+                ..
+            })
+        ));
+    }
+
+    #[parameterized(byte = {
+        12, 13, 14, 15
+    })]
+    fn read_rejects_values_that_are_not_in_the_enums(byte: usize) {
+        let mut binary_data = [0u8; 20];
+        binary_data[12] = b'J';
+        binary_data[byte] = 0x7f;
+        let region = given_a_region(&binary_data);
+
+        let result =
+            PerfDataEntryHeader::read(&ByteReader::new(region.as_ref(), Endianness::BigEndian));
+
+        assert!(matches!(
+            result,
+            Err(Error::InvalidField { value: 0x7f, .. })
+        ));
+    }
 }
@@ -1,8 +1,13 @@
-use std::{collections::HashMap, os::raw::c_void, ptr::NonNull};
+use std::{collections::HashMap, sync::Arc};
 
 use crate::{
-    errors::Error, jvm_monitor::Entry, perf_data::PerfDataEntryHeader,
-    safish_pointer::SafishPointer,
+    PerfDataSource,
+    byte_reader::ByteReader,
+    errors::Error,
+    jvm_clock,
+    jvm_monitor::Entry,
+    parsing::{Diagnostic, ParsingMode},
+    perf_data::PerfDataEntryHeader,
 };
 
 /// /**
@@ -28,6 +33,19 @@ enum MagicNumber {
     BigEndian = BIG_ENDIAN_MAGIC_NUMBER,
 }
 
+impl TryFrom<u32> for MagicNumber {
+    type Error = Error;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            LITTLE_ENDIAN_MAGIC_NUMBER => Ok(MagicNumber::LittleEndian),
+            BIG_ENDIAN_MAGIC_NUMBER => Ok(MagicNumber::BigEndian),
+            _ => Err(Error::InvalidMagicNumber(value)),
+        }
+    }
+}
+
+/// ```text
 /// /* Byte order of the PerfData memory region. The byte order is exposed in
 ///  * the PerfData memory region as the data in the memory region may have
 ///  * been generated by a little endian JVM implementation. Tracking the byte
@@ -39,13 +57,41 @@ enum MagicNumber {
 ///  */
 /// #define PERFDATA_BIG_ENDIAN     0
 /// #define PERFDATA_LITTLE_ENDIAN  1
+/// ```
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub(crate) enum Endianness {
+pub enum Endianness {
     BigEndian = 0,
     LittleEndian = 1,
 }
 
+impl Endianness {
+    /// Byte order of the machine running the crate.
+    pub fn native() -> Self {
+        if cfg!(target_endian = "little") {
+            Endianness::LittleEndian
+        } else {
+            Endianness::BigEndian
+        }
+    }
+}
+
+impl TryFrom<u8> for Endianness {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Endianness::BigEndian),
+            1 => Ok(Endianness::LittleEndian),
+            _ => Err(Error::InvalidField {
+                field: "byte_order",
+                value: value as i64,
+            }),
+        }
+    }
+}
+
+/// ```text
 /// /*
 ///  * The PerfDataPrologue structure is known by the PerfDataBuffer Java class
 ///  * libraries that read the PerfData memory region. The size and the position
@@ -67,6 +113,7 @@ pub(crate) enum Endianness {
 ///     jint   entry_offset;       // offset of the first PerfDataEntry
 ///     jint   num_entries;        // number of allocated PerfData entries
 ///   } PerfDataPrologue;
+/// ```
 #[repr(C)]
 #[derive(Debug)]
 pub(crate) struct PerfDataProlog {
@@ -82,178 +129,323 @@ pub(crate) struct PerfDataProlog {
     num_entries: i32,
 }
 
+const PROLOG_SIZE: usize = size_of::<PerfDataProlog>();
+
 impl PerfDataProlog {
+    /// Reads the entries of the region, along with the diagnostics of the entries
+    /// skipped in lenient mode.
     pub(crate) fn read_entries(
-        prolog_addr: &NonNull<c_void>,
-        length: usize,
-    ) -> Result<HashMap<String, Entry>, Error> {
-        let prolog = Self::new(prolog_addr).validate()?.validate_length(length)?;
-        prolog.map_entries(prolog_addr)
+        region: &Arc<dyn PerfDataSource>,
+        mode: ParsingMode,
+    ) -> Result<(HashMap<String, Entry>, Vec<Diagnostic>), Error> {
+        let prolog = Self::read(region.as_ref())?
+            .validate()?
+            .validate_length(region.len())?;
+        let (mut entries, diagnostics) = prolog.map_entries(region, mode)?;
+        if let Some(frequency) = jvm_clock::frequency_of(&entries) {
+            for entry in entries.values_mut() {
+                entry.set_frequency(frequency);
+            }
+        }
+        Ok((entries, diagnostics))
     }
 
-    fn new(prolog_addr: &NonNull<c_void>) -> Self {
-        unsafe { (prolog_addr.as_ptr() as *const PerfDataProlog).read_volatile() }
+    /// Number of bytes actually used by the JVM, as told by the prolog the bytes start
+    /// with once validated against the length of the region.
+    pub(crate) fn used_bytes(bytes: &dyn PerfDataSource, length: usize) -> Result<usize, Error> {
+        let prolog = Self::read(bytes)?.validate()?.validate_length(length)?;
+        Ok(prolog.used as usize)
     }
 
+    /// Decodes the prolog field by field in the byte order it declares, as a corrupted
+    /// region may hold values that are not valid for the enums it is made of.
+    fn read(region: &dyn PerfDataSource) -> Result<Self, Error> {
+        let prolog = ByteReader::new(region, Endianness::BigEndian).slice(0, PROLOG_SIZE)?;
+        let byte_order = Endianness::try_from(prolog.u8(4)?)?;
+        let prolog = prolog.with_endianness(byte_order);
+        Ok(Self {
+            magic: MagicNumber::try_from(prolog.u32(0)?)?,
+            byte_order,
+            major_version: prolog.u8(5)?,
+            minor_version: prolog.u8(6)?,
+            accessible: prolog.u8(7)?,
+            used: prolog.i32(8)?,
+            overflow: prolog.i32(12)?,
+            mod_time_stamp: prolog.i64(16)?,
+            entry_offset: prolog.i32(24)?,
+            num_entries: prolog.i32(28)?,
+        })
+    }
+
+    /// Ensures the entries lie in the bytes available, and that there cannot be more
+    /// of them than headers fitting in the region.
     fn validate_length(self, length: usize) -> Result<Self, crate::errors::Error> {
-        // Ensure there are enough bytes to read the entries.
-        if length >= self.used as usize {
-            Ok(self)
-        } else {
+        if self.used < PROLOG_SIZE as i32 {
+            Err(Self::invalid("used", self.used))
+        } else if self.used as usize > length {
             Err(Error::WontBeAbleToRead)
+        } else if self.entry_offset < PROLOG_SIZE as i32 || self.entry_offset > self.used {
+            Err(Self::invalid("entry_offset", self.entry_offset))
+        } else if self.num_entries < 0
+            || self.num_entries as usize
+                > (self.used - self.entry_offset) as usize / size_of::<PerfDataEntryHeader>()
+        {
+            Err(Self::invalid("num_entries", self.num_entries))
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn invalid(field: &'static str, value: i32) -> Error {
+        Error::InvalidField {
+            field,
+            value: value as i64,
         }
     }
 
     /// Verifies the struct matches the specs used to create the crate
     fn validate(self) -> Result<Self, crate::errors::Error> {
         if self.major_version != MAJOR_VERSION {
-            Err(crate::errors::Error::UnsupportedMajorVersion)
+            Err(crate::errors::Error::UnsupportedMajorVersion(
+                self.major_version,
+            ))
         } else if self.minor_version != MINOR_VERSION {
-            Err(crate::errors::Error::UnsupportedMinorVersion)
-        } else if self.byte_order == Endianness::BigEndian && self.magic != MagicNumber::BigEndian {
-            Err(crate::errors::Error::InvalidMagicNumber)
-        } else if self.byte_order == Endianness::LittleEndian
-            && self.magic != MagicNumber::LittleEndian
+            Err(crate::errors::Error::UnsupportedMinorVersion(
+                self.minor_version,
+            ))
+        } else if (self.byte_order == Endianness::BigEndian && self.magic != MagicNumber::BigEndian)
+            || (self.byte_order == Endianness::LittleEndian
+                && self.magic != MagicNumber::LittleEndian)
         {
-            Err(crate::errors::Error::InvalidMagicNumber)
+            Err(crate::errors::Error::InvalidMagicNumber(self.magic as u32))
         } else {
             Ok(self)
         }
     }
 
-    fn map_entries(self, prolog_addr: &NonNull<c_void>) -> Result<HashMap<String, Entry>, Error> {
-        let entries_ptr = self.entries_ptr(&prolog_addr)?;
+    fn map_entries(
+        self,
+        region: &Arc<dyn PerfDataSource>,
+        mode: ParsingMode,
+    ) -> Result<(HashMap<String, Entry>, Vec<Diagnostic>), Error> {
+        let used =
+            ByteReader::new(region.as_ref(), self.byte_order).slice(0, self.used as usize)?;
         let entries_count = self.num_entries as usize;
         let mut entries: HashMap<String, Entry> = HashMap::with_capacity(entries_count);
-        let mut offset = 0 as usize;
-        for _ in 0..entries_count {
-            let entry_ptr: SafishPointer<PerfDataEntryHeader> =
-                entries_ptr.clone().add(offset)?.convert()?;
-            let header = entry_ptr.read();
-            if header.is_variable_entry() {
-                let (name, entry) = header.read_variable_entry(entry_ptr)?;
-                entries.insert(name, Entry::Variable(entry));
-            } else {
-                let (name, entry) = header.read_constant_entry(entry_ptr)?;
-                entries.insert(name, Entry::Constant(entry));
+        let mut diagnostics = vec![];
+        let mut entry_offset = self.entry_offset as usize;
+        for index in 0..entries_count {
+            let header = used.skip(entry_offset).and_then(|entry| {
+                let header = PerfDataEntryHeader::read(&entry)?;
+                header.validate_length(entry.len())?;
+                Ok((entry.slice(0, header.entry_length() as usize)?, header))
+            });
+            let (entry, header) = match header {
+                Ok(header) => header,
+                Err(e) => {
+                    // Without a header, the following entries cannot be found.
+                    mode.handle(&mut diagnostics, index, entry_offset, None, e)?;
+                    break;
+                }
             };
-            offset += header.entry_length() as usize;
+            let result = header.validate().and_then(|_| {
+                if header.is_variable_entry() {
+                    header
+                        .read_variable_entry(&entry, region)
+                        .map(|(name, entry)| (name, Entry::Variable(entry)))
+                } else {
+                    header
+                        .read_constant_entry(&entry)
+                        .map(|(name, entry)| (name, Entry::Constant(entry)))
+                }
+            });
+            match result {
+                Ok((name, entry)) => {
+                    entries.insert(name, entry);
+                }
+                Err(e) => {
+                    let name = header.read_name(&entry).ok();
+                    mode.handle(&mut diagnostics, index, entry_offset, name, e)?;
+                }
+            }
+            entry_offset += header.entry_length() as usize;
         }
 
-        Ok(entries)
-    }
-
-    fn entries_ptr(&self, prolog_addr: &NonNull<c_void>) -> Result<SafishPointer<u8>, Error> {
-        SafishPointer::new(
-            prolog_addr.as_ptr() as *const u8,
-            self.used as usize,
-            self.byte_order,
-        )
-        .and_then(|ptr| ptr.add(self.entry_offset as usize))
+        Ok((entries, diagnostics))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{fs, path::PathBuf, sync::Arc};
+
     use parameterized::parameterized;
 
-    use crate::perf_data::{
-        Endianness,
-        perf_data_prolog::{MAJOR_VERSION, MINOR_VERSION, MagicNumber, PerfDataProlog},
+    use crate::{
+        EntryValue, PerfDataBuilder, PerfDataSource, Unit, Variability,
+        errors::Error,
+        parsing::ParsingMode,
+        perf_data::{Endianness, perf_data_prolog::PerfDataProlog},
     };
 
-    #[test]
-    fn validate_is_success() {
-        let tested_prolog = PerfDataProlog {
-            magic: super::MagicNumber::BigEndian,
-            byte_order: super::Endianness::BigEndian,
-            major_version: MAJOR_VERSION,
-            minor_version: MINOR_VERSION,
-            accessible: 0 as u8,
-            used: size_of::<PerfDataProlog>() as i32,
-            overflow: 0,
-            mod_time_stamp: 12345,
-            entry_offset: 0,
-            num_entries: 0,
-        };
-        let validation_result = tested_prolog.validate();
-
-        assert!(validation_result.is_ok());
+    /// The regions of the fuzzing corpus, valid ones are prefixed with valid_.
+    fn given_the_corpus() -> Vec<(String, Arc<dyn PerfDataSource>)> {
+        let corpus = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fuzz/corpus/parse");
+        fs::read_dir(corpus)
+            .unwrap()
+            .map(|file| {
+                let path = file.unwrap().path();
+                let bytes = fs::read(&path).unwrap();
+                (
+                    path.file_name().unwrap().to_string_lossy().to_string(),
+                    Arc::new(bytes) as Arc<dyn PerfDataSource>,
+                )
+            })
+            .collect()
+    }
+
+    fn read_entries(region: &Arc<dyn PerfDataSource>, mode: ParsingMode) -> Result<usize, Error> {
+        PerfDataProlog::read_entries(region, mode).map(|(entries, _)| entries.len())
+    }
+
+    /// Lays out a region without entries, whose prolog the tests then alter.
+    fn given_a_built_region(endianness: Endianness) -> Vec<u8> {
+        PerfDataBuilder::new(endianness).build().unwrap()
+    }
+
+    fn validate(bytes: &Vec<u8>) -> Result<PerfDataProlog, Error> {
+        PerfDataProlog::read(bytes).unwrap().validate()
+    }
+
+    #[parameterized(endianness = {
+        Endianness::BigEndian, Endianness::LittleEndian
+    })]
+    fn validate_is_success(endianness: Endianness) {
+        let bytes = given_a_built_region(endianness);
+
+        assert!(validate(&bytes).is_ok());
     }
 
     #[test]
     fn validate_is_an_error_when_major_version_value_is_incorrect() {
-        let tested_prolog = PerfDataProlog {
-            magic: super::MagicNumber::BigEndian,
-            byte_order: super::Endianness::BigEndian,
-            major_version: 123 as u8,
-            minor_version: MINOR_VERSION,
-            accessible: 0 as u8,
-            used: size_of::<PerfDataProlog>() as i32,
-            overflow: 0,
-            mod_time_stamp: 12345,
-            entry_offset: 0,
-            num_entries: 0,
-        };
-        let validation_result = tested_prolog.validate();
-
-        assert!(validation_result.is_err());
+        let mut bytes = given_a_built_region(Endianness::BigEndian);
+        bytes[5] = 123;
+
         assert!(matches!(
-            validation_result.err().unwrap(),
-            crate::errors::Error::UnsupportedMajorVersion
+            validate(&bytes),
+            Err(Error::UnsupportedMajorVersion(123))
         ));
     }
 
     #[test]
     fn validate_is_an_error_when_minor_version_value_is_incorrect() {
-        let tested_prolog = PerfDataProlog {
-            magic: super::MagicNumber::BigEndian,
-            byte_order: super::Endianness::BigEndian,
-            major_version: MAJOR_VERSION,
-            minor_version: 123 as u8,
-            accessible: 0 as u8,
-            used: size_of::<PerfDataProlog>() as i32,
-            overflow: 0,
-            mod_time_stamp: 12345,
-            entry_offset: 0,
-            num_entries: 0,
-        };
-        let validation_result = tested_prolog.validate();
-
-        assert!(validation_result.is_err());
+        let mut bytes = given_a_built_region(Endianness::BigEndian);
+        bytes[6] = 123;
+
+        assert!(matches!(
+            validate(&bytes),
+            Err(Error::UnsupportedMinorVersion(123))
+        ));
+    }
+
+    #[parameterized(endianness = {
+        Endianness::BigEndian, Endianness::LittleEndian
+    })]
+    fn validate_is_an_error_when_magic_number_does_not_match_byte_order(endianness: Endianness) {
+        let mut bytes = given_a_built_region(endianness);
+        // Read in the byte order of the region, the reversed magic number is the other one.
+        bytes[0..4].reverse();
+
         assert!(matches!(
-            validation_result.err().unwrap(),
-            crate::errors::Error::UnsupportedMinorVersion
+            validate(&bytes),
+            Err(Error::InvalidMagicNumber(_))
         ));
     }
 
-    #[parameterized(magic_number = {
-        MagicNumber::BigEndian, MagicNumber::LittleEndian
-    }, byte_order = {
+    #[test]
+    fn valid_corpus_regions_are_read() {
+        for (name, region) in given_the_corpus() {
+            if name.starts_with("valid_") {
+                assert!(read_entries(&region, ParsingMode::Strict).is_ok(), "{name}");
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_corpus_regions_are_rejected() {
+        for (name, region) in given_the_corpus() {
+            if !name.starts_with("valid_") {
+                assert!(
+                    read_entries(&region, ParsingMode::Strict).is_err(),
+                    "{name}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_corpus_regions_are_read_leniently_without_their_invalid_entries() {
+        for (name, region) in given_the_corpus() {
+            if let Ok((entries, diagnostics)) =
+                PerfDataProlog::read_entries(&region, ParsingMode::Lenient)
+            {
+                assert!(
+                    name.starts_with("valid_") || !diagnostics.is_empty(),
+                    "{name}"
+                );
+                for entry in entries.values() {
+                    assert!(entry.value().is_ok(), "{name}");
+                }
+            }
+        }
+    }
+
+    #[parameterized(endianness = {
         Endianness::LittleEndian, Endianness::BigEndian
     })]
-    fn validate_is_an_error_when_magic_number_does_not_match_byte_order(
-        magic_number: MagicNumber,
-        byte_order: Endianness,
-    ) {
-        let tested_prolog = PerfDataProlog {
-            magic: magic_number,
-            byte_order: byte_order,
-            major_version: MAJOR_VERSION,
-            minor_version: MINOR_VERSION,
-            accessible: 0 as u8,
-            used: size_of::<PerfDataProlog>() as i32,
-            overflow: 0,
-            mod_time_stamp: 12345,
-            entry_offset: 0,
-            num_entries: 0,
-        };
-        let validation_result = tested_prolog.validate();
-
-        assert!(validation_result.is_err());
+    fn read_entries_reads_built_regions(endianness: Endianness) {
+        let bytes = PerfDataBuilder::new(endianness)
+            .entry(
+                "sun.rt.javaCommand",
+                EntryValue::String("org.example.Main".to_string()),
+                Unit::String,
+                Variability::Constant,
+            )
+            .entry(
+                "sun.gc.generation.0.space.0.capacity",
+                EntryValue::Long(1 << 20),
+                Unit::Bytes,
+                Variability::Variable,
+            )
+            .entry(
+                "sun.gc.tlab.alloc",
+                EntryValue::IntVec(vec![1, 2, 3]),
+                Unit::Bytes,
+                Variability::Variable,
+            )
+            .capacity(32 * 1024)
+            .build()
+            .unwrap();
+        let region: Arc<dyn PerfDataSource> = Arc::new(bytes);
+
+        let (entries, diagnostics) =
+            PerfDataProlog::read_entries(&region, ParsingMode::Strict).unwrap();
+
+        assert_eq!(3, entries.len());
+        assert!(diagnostics.is_empty());
+        assert!(matches!(
+            entries["sun.gc.tlab.alloc"].value(),
+            Ok(EntryValue::IntVec(values)) if values == [1, 2, 3]
+        ));
+    }
+
+    #[test]
+    fn read_entries_rejects_regions_shorter_than_the_prolog() {
+        let region: Arc<dyn PerfDataSource> = Arc::new(vec![0u8; 16]);
+
         assert!(matches!(
-            validation_result.err().unwrap(),
-            crate::errors::Error::InvalidMagicNumber
+            read_entries(&region, ParsingMode::Lenient),
+            Err(Error::WontBeAbleToRead)
         ));
     }
 }
@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+
+use crate::{
+    EntryValue,
+    data_pointer::DataPointer,
+    errors::Error,
+    perf_data::{
+        DataType, Endianness, Flag, PerfDataEntryHeader, PerfDataProlog, Unit, Variability,
+    },
+};
+
+const PROLOG_SIZE: usize = size_of::<PerfDataProlog>();
+const HEADER_SIZE: usize = size_of::<PerfDataEntryHeader>();
+
+/// Entry of a region being encoded. Its type and length are set by its first value.
+struct EntryDefinition {
+    name: String,
+    value: EntryValue,
+    layout: Option<(DataType, Option<usize>)>,
+    unit: Unit,
+    variability: Variability,
+}
+
+/// Lays out hsperfdata regions the way HotSpot does, so that they can be read back by
+/// the crate as well as by the JDK tools.
+pub(crate) struct RegionEncoder {
+    endianness: Endianness,
+    entries: Vec<EntryDefinition>,
+}
+
+impl RegionEncoder {
+    pub(crate) fn new(endianness: Endianness) -> Self {
+        Self {
+            endianness,
+            entries: vec![],
+        }
+    }
+
+    /// Adds an entry, or replaces the one with the same name. Strings get room for
+    /// at least 255 bytes, so that they can be set to longer values.
+    pub(crate) fn add(
+        &mut self,
+        name: &str,
+        value: EntryValue,
+        unit: Unit,
+        variability: Variability,
+    ) {
+        let layout = Self::layout_of(&value).map(|(data_type, length)| match value {
+            EntryValue::String(_) => (data_type, length.map(|length| length.max(256))),
+            _ => (data_type, length),
+        });
+        let definition = EntryDefinition {
+            name: name.to_string(),
+            value,
+            layout,
+            unit,
+            variability,
+        };
+        match self.entries.iter_mut().find(|entry| entry.name == name) {
+            Some(entry) => *entry = definition,
+            None => self.entries.push(definition),
+        }
+    }
+
+    #[cfg(any(test, feature = "testing"))]
+    pub(crate) fn value(&self, name: &str) -> Option<&EntryValue> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .map(|entry| &entry.value)
+    }
+
+    /// Changes the value of an entry, which must be of the same type and fit in its length.
+    #[cfg(any(test, feature = "testing"))]
+    pub(crate) fn set(&mut self, name: &str, value: EntryValue) -> Result<(), Error> {
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.name == name)
+            .ok_or_else(|| Error::MissingEntry {
+                name: name.to_string(),
+            })?;
+        match (Self::layout_of(&value), entry.layout) {
+            (Some((data_type, None)), Some((expected, None))) if data_type == expected => {}
+            (Some((data_type, Some(length))), Some((expected, Some(room))))
+                if data_type == expected && length <= room => {}
+            _ => return Err(Error::ValueDoesNotFit),
+        }
+        entry.value = value;
+        Ok(())
+    }
+
+    /// The bytes of the region holding all the entries.
+    pub(crate) fn encode(&self) -> Result<Vec<u8>, Error> {
+        self.encode_with_pointers().map(|(bytes, _)| bytes)
+    }
+
+    /// The bytes of the region, along with the location of the value of each entry.
+    pub(crate) fn encode_with_pointers(
+        &self,
+    ) -> Result<(Vec<u8>, HashMap<String, DataPointer>), Error> {
+        let mut bytes = vec![0u8; PROLOG_SIZE];
+        let mut data_pointers = HashMap::with_capacity(self.entries.len());
+        for entry in &self.entries {
+            let data_pointer = self.encode_entry(entry, &mut bytes)?;
+            data_pointers.insert(entry.name.clone(), data_pointer);
+        }
+        let used = self.int(bytes.len())?;
+        let num_entries = self.int(self.entries.len())?;
+        let entry_offset = self.int(PROLOG_SIZE)?;
+        // The magic number is always laid out as 0xcafec0c0, in any byte order.
+        bytes[0..4].copy_from_slice(&[0xca, 0xfe, 0xc0, 0xc0]);
+        bytes[4] = self.endianness as u8;
+        bytes[5] = 2;
+        bytes[6] = 0;
+        bytes[7] = 1;
+        bytes[8..12].copy_from_slice(&used);
+        bytes[24..28].copy_from_slice(&entry_offset);
+        bytes[28..32].copy_from_slice(&num_entries);
+        Ok((bytes, data_pointers))
+    }
+
+    /// Appends the header, the name and the value of the entry. The value is aligned on the
+    /// size of its type and the entry on 8 bytes, as in PerfData::create_entry.
+    fn encode_entry(
+        &self,
+        entry: &EntryDefinition,
+        bytes: &mut Vec<u8>,
+    ) -> Result<DataPointer, Error> {
+        let (data_type, vector_length) = entry.layout.ok_or(Error::ValueDoesNotFit)?;
+        let data_offset = (HEADER_SIZE + entry.name.len() + 1).next_multiple_of(data_type.size());
+        let data_length = vector_length
+            .unwrap_or(1)
+            .checked_mul(data_type.size())
+            .ok_or(Error::ValueDoesNotFit)?;
+        let entry_length = (data_offset + data_length).next_multiple_of(size_of::<i64>());
+
+        let start = bytes.len();
+        bytes.resize(start + entry_length, 0);
+        let header = &mut bytes[start..start + HEADER_SIZE];
+        header[0..4].copy_from_slice(&self.int(entry_length)?);
+        header[4..8].copy_from_slice(&self.int(HEADER_SIZE)?);
+        header[8..12].copy_from_slice(&self.int(vector_length.unwrap_or(0))?);
+        header[12] = data_type as u8;
+        header[13] = Flag::of(&entry.name) as u8;
+        header[14] = entry.unit as u8;
+        header[15] = entry.variability as u8;
+        header[16..20].copy_from_slice(&self.int(data_offset)?);
+        bytes[start + HEADER_SIZE..start + HEADER_SIZE + entry.name.len()]
+            .copy_from_slice(entry.name.as_bytes());
+        let data = self.encode_value(&entry.value);
+        bytes[start + data_offset..start + data_offset + data.len()].copy_from_slice(&data);
+        Ok(match vector_length {
+            None => DataPointer::new_scalar(data_type, start + data_offset, self.endianness),
+            Some(length) => {
+                DataPointer::new_vector(data_type, length, start + data_offset, self.endianness)
+            }
+        })
+    }
+
+    /// Type and vector length of the entry holding the value.
+    fn layout_of(value: &EntryValue) -> Option<(DataType, Option<usize>)> {
+        let (data_type, length) = match value {
+            EntryValue::Boolean(_) => (DataType::Boolean, None),
+            EntryValue::Byte(_) => (DataType::Byte, None),
+            EntryValue::Char(_) => (DataType::Char, None),
+            EntryValue::Short(_) => (DataType::Short, None),
+            EntryValue::Int(_) => (DataType::Int, None),
+            EntryValue::Long(_) => (DataType::Long, None),
+            EntryValue::Float(_) => (DataType::Float, None),
+            EntryValue::Double(_) => (DataType::Double, None),
+            EntryValue::BooleanVec(values) => (DataType::Boolean, Some(values.len())),
+            EntryValue::String(value) => (DataType::Byte, Some(value.chars().count() + 1)),
+            EntryValue::CharVec(values) => (DataType::Char, Some(values.len())),
+            EntryValue::ShortVec(values) => (DataType::Short, Some(values.len())),
+            EntryValue::IntVec(values) => (DataType::Int, Some(values.len())),
+            EntryValue::LongVec(values) => (DataType::Long, Some(values.len())),
+            EntryValue::FloatVec(values) => (DataType::Float, Some(values.len())),
+            EntryValue::DoubleVec(values) => (DataType::Double, Some(values.len())),
+            EntryValue::NotSet => return None,
+        };
+        // A vector of no item would be read as a scalar.
+        (length != Some(0)).then_some((data_type, length))
+    }
+
+    /// Encodes the value the way EntryValue::new decodes it. Booleans are decoded as true
+    /// when their byte is 0, chars are UTF-16 code units and strings are Latin-1 bytes.
+    fn encode_value(&self, value: &EntryValue) -> Vec<u8> {
+        let boolean = |value: &bool| u8::from(!value);
+        let char = |value: &char| u16::try_from(u32::from(*value)).unwrap_or(0xfffd);
+        match value {
+            EntryValue::Boolean(value) => vec![boolean(value)],
+            EntryValue::Byte(value) => vec![*value],
+            EntryValue::Char(value) => {
+                self.ordered(char(value), u16::to_le_bytes, u16::to_be_bytes)
+            }
+            EntryValue::Short(value) => self.ordered(*value, i16::to_le_bytes, i16::to_be_bytes),
+            EntryValue::Int(value) => self.ordered(*value, i32::to_le_bytes, i32::to_be_bytes),
+            EntryValue::Long(value) => self.ordered(*value, i64::to_le_bytes, i64::to_be_bytes),
+            EntryValue::Float(value) => self.ordered(*value, f32::to_le_bytes, f32::to_be_bytes),
+            EntryValue::Double(value) => self.ordered(*value, f64::to_le_bytes, f64::to_be_bytes),
+            EntryValue::BooleanVec(values) => values.iter().map(boolean).collect(),
+            EntryValue::String(value) => Self::latin1(value),
+            EntryValue::CharVec(values) => values
+                .iter()
+                .flat_map(|value| self.ordered(char(value), u16::to_le_bytes, u16::to_be_bytes))
+                .collect(),
+            EntryValue::ShortVec(values) => values
+                .iter()
+                .flat_map(|value| self.ordered(*value, i16::to_le_bytes, i16::to_be_bytes))
+                .collect(),
+            EntryValue::IntVec(values) => values
+                .iter()
+                .flat_map(|value| self.ordered(*value, i32::to_le_bytes, i32::to_be_bytes))
+                .collect(),
+            EntryValue::LongVec(values) => values
+                .iter()
+                .flat_map(|value| self.ordered(*value, i64::to_le_bytes, i64::to_be_bytes))
+                .collect(),
+            EntryValue::FloatVec(values) => values
+                .iter()
+                .flat_map(|value| self.ordered(*value, f32::to_le_bytes, f32::to_be_bytes))
+                .collect(),
+            EntryValue::DoubleVec(values) => values
+                .iter()
+                .flat_map(|value| self.ordered(*value, f64::to_le_bytes, f64::to_be_bytes))
+                .collect(),
+            EntryValue::NotSet => vec![],
+        }
+    }
+
+    /// The Latin-1 bytes of the string, with '?' for the characters that have none.
+    pub(crate) fn latin1(value: &str) -> Vec<u8> {
+        value
+            .chars()
+            .map(|c| u8::try_from(c).unwrap_or(b'?'))
+            .collect()
+    }
+
+    fn ordered<T, const N: usize>(
+        &self,
+        value: T,
+        to_le_bytes: fn(T) -> [u8; N],
+        to_be_bytes: fn(T) -> [u8; N],
+    ) -> Vec<u8> {
+        match self.endianness {
+            Endianness::LittleEndian => to_le_bytes(value).to_vec(),
+            Endianness::BigEndian => to_be_bytes(value).to_vec(),
+        }
+    }
+
+    /// Encodes a length or an offset as a jint.
+    fn int(&self, value: usize) -> Result<Vec<u8>, Error> {
+        let value = i32::try_from(value).map_err(|_| Error::ValueDoesNotFit)?;
+        Ok(self.ordered(value, i32::to_le_bytes, i32::to_be_bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parameterized::parameterized;
+
+    use crate::{
+        EntryValue, Error, Snapshot,
+        perf_data::{Endianness, RegionEncoder, Unit, Variability},
+    };
+
+    fn values() -> Vec<EntryValue> {
+        vec![
+            EntryValue::Boolean(true),
+            EntryValue::Byte(42),
+            EntryValue::Char('é'),
+            EntryValue::Short(-2),
+            EntryValue::Int(123456),
+            EntryValue::Long(-1234567890123),
+            EntryValue::Float(1.5),
+            EntryValue::Double(-2.25),
+            EntryValue::BooleanVec(vec![true, false]),
+            EntryValue::String("Allocation Failure".to_string()),
+            EntryValue::CharVec(vec!['a', 'é']),
+            EntryValue::ShortVec(vec![1, -1, 3]),
+            EntryValue::IntVec(vec![1, -1]),
+            EntryValue::LongVec(vec![i64::MIN, i64::MAX]),
+            EntryValue::FloatVec(vec![0.5]),
+            EntryValue::DoubleVec(vec![0.25, -0.75]),
+        ]
+    }
+
+    fn given_an_encoder(endianness: Endianness) -> RegionEncoder {
+        let mut encoder = RegionEncoder::new(endianness);
+        for (index, value) in values().into_iter().enumerate() {
+            encoder.add(
+                &format!("test.entry.{index}"),
+                value,
+                Unit::Events,
+                Variability::Variable,
+            );
+        }
+        encoder
+    }
+
+    #[parameterized(endianness = {
+        Endianness::LittleEndian, Endianness::BigEndian
+    })]
+    fn encoded_values_are_read_back(endianness: Endianness) {
+        let bytes = given_an_encoder(endianness).encode().unwrap();
+
+        let snapshot = Snapshot::from_bytes(&bytes).unwrap();
+
+        assert_eq!(values().len(), snapshot.entries().len());
+        for (index, value) in values().into_iter().enumerate() {
+            let entry = &snapshot.entries()[&format!("test.entry.{index}")];
+            assert_eq!(
+                format!("{value:?}"),
+                format!("{:?}", entry.value().unwrap())
+            );
+            assert_eq!(Unit::Events, entry.unit());
+        }
+    }
+
+    #[test]
+    fn entries_are_aligned_on_8_bytes() {
+        let bytes = given_an_encoder(Endianness::LittleEndian).encode().unwrap();
+
+        assert_eq!(0, bytes.len() % 8);
+    }
+
+    #[test]
+    fn set_changes_the_encoded_value() {
+        let mut encoder = given_an_encoder(Endianness::BigEndian);
+
+        encoder.set("test.entry.5", EntryValue::Long(7)).unwrap();
+        encoder
+            .set(
+                "test.entry.9",
+                EntryValue::String("System.gc()".to_string()),
+            )
+            .unwrap();
+
+        let snapshot = Snapshot::from_bytes(&encoder.encode().unwrap()).unwrap();
+        assert!(matches!(
+            snapshot.entries()["test.entry.5"].value(),
+            Ok(EntryValue::Long(7))
+        ));
+        assert!(matches!(
+            snapshot.entries()["test.entry.9"].value(),
+            Ok(EntryValue::String(cause)) if cause == "System.gc()"
+        ));
+    }
+
+    #[test]
+    fn set_refuses_values_that_do_not_fit() {
+        let mut encoder = given_an_encoder(Endianness::BigEndian);
+
+        assert!(matches!(
+            encoder.set("test.entry.5", EntryValue::Int(7)),
+            Err(Error::ValueDoesNotFit)
+        ));
+        assert!(matches!(
+            encoder.set("test.entry.13", EntryValue::LongVec(vec![1, 2, 3])),
+            Err(Error::ValueDoesNotFit)
+        ));
+        assert!(matches!(
+            encoder.set("test.missing", EntryValue::Int(7)),
+            Err(Error::MissingEntry { name }) if name == "test.missing"
+        ));
+    }
+
+    #[test]
+    fn values_without_a_layout_are_not_encoded() {
+        for value in [EntryValue::NotSet, EntryValue::LongVec(vec![])] {
+            let mut encoder = RegionEncoder::new(Endianness::LittleEndian);
+            encoder.add("test.entry", value, Unit::None, Variability::Constant);
+
+            assert!(matches!(encoder.encode(), Err(Error::ValueDoesNotFit)));
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use crate::errors::Error;
+
 ///     // the Units enum must be kept in synchronization with the
 ///     // the com.sun.hotspot.perfdata.Units class
 ///     enum Units {
@@ -20,3 +22,23 @@ pub enum Unit {
     String = 5,
     Hertz = 6,
 }
+
+impl TryFrom<u8> for Unit {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Unit::Invalid),
+            1 => Ok(Unit::None),
+            2 => Ok(Unit::Bytes),
+            3 => Ok(Unit::Ticks),
+            4 => Ok(Unit::Events),
+            5 => Ok(Unit::String),
+            6 => Ok(Unit::Hertz),
+            _ => Err(Error::InvalidField {
+                field: "data_units",
+                value: value as i64,
+            }),
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use crate::errors::Error;
+
 /// // the Variability enum must be kept in synchronization with the
 ///     // the com.sun.hotspot.perfdata.Variability class
 ///     enum Variability {
@@ -14,3 +16,20 @@ pub enum Variability {
     Monotonic = 2,
     Variable = 3,
 }
+
+impl TryFrom<u8> for Variability {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Variability::Invalid),
+            1 => Ok(Variability::Constant),
+            2 => Ok(Variability::Monotonic),
+            3 => Ok(Variability::Variable),
+            _ => Err(Error::InvalidField {
+                field: "data_variability",
+                value: value as i64,
+            }),
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use std::io::Write;
+
+use crate::{
+    EntryValue,
+    errors::Error,
+    perf_data::{Endianness, RegionEncoder, Unit, Variability},
+};
+
+/// Builder of complete hsperfdata regions, laid out byte for byte as HotSpot does, to be
+/// used as fixtures or replayed.
+///
+/// ```
+/// use hsperf::{Endianness, EntryValue, PerfDataBuilder, Snapshot, Unit, Variability};
+///
+/// let bytes = PerfDataBuilder::new(Endianness::BigEndian)
+///     .entry("sun.os.hrt.frequency", EntryValue::Long(1_000_000_000), Unit::Hertz, Variability::Constant)
+///     .capacity(32 * 1024)
+///     .build()?;
+///
+/// let snapshot = Snapshot::from_bytes(&bytes)?;
+/// assert_eq!(1, snapshot.entries().len());
+/// # Ok::<(), hsperf::Error>(())
+/// ```
+pub struct PerfDataBuilder {
+    encoder: RegionEncoder,
+    capacity: usize,
+}
+
+impl PerfDataBuilder {
+    /// A builder of regions in the byte order, without entries.
+    pub fn new(endianness: Endianness) -> Self {
+        Self {
+            encoder: RegionEncoder::new(endianness),
+            capacity: 0,
+        }
+    }
+
+    /// Refines the region with an entry, whose type is the one of its value, or replaces
+    /// the entry with the same name. Entries are laid out in the order they are added.
+    pub fn entry(
+        mut self,
+        name: &str,
+        value: EntryValue,
+        unit: Unit,
+        variability: Variability,
+    ) -> Self {
+        self.encoder.add(name, value, unit, variability);
+        self
+    }
+
+    /// Refines the region so that it is padded with zeros up to the capacity, like the
+    /// files of PerfDataMemorySize bytes published by the JVMs.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// The bytes of the region.
+    pub fn build(&self) -> Result<Vec<u8>, Error> {
+        let mut bytes = self.encoder.encode()?;
+        if bytes.len() < self.capacity {
+            bytes.resize(self.capacity, 0);
+        }
+        Ok(bytes)
+    }
+
+    /// Writes the bytes of the region, e.g. to an hsperfdata file.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        writer
+            .write_all(&self.build()?)
+            .map_err(Error::FailedToWrite)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, fs};
+
+    use proptest::{collection::btree_map, prelude::any};
+    use test_strategy::proptest;
+
+    use crate::{
+        Endianness, EntryValue, Error, FileChecks, JavaVirtualMachine, PerfDataBuilder, Snapshot,
+        Unit, Variability,
+    };
+
+    fn given_a_builder(endianness: Endianness) -> PerfDataBuilder {
+        PerfDataBuilder::new(endianness)
+            .entry(
+                "sun.rt.javaCommand",
+                EntryValue::String("org.example.Main".to_string()),
+                Unit::String,
+                Variability::Constant,
+            )
+            .entry(
+                "sun.gc.collector.0.invocations",
+                EntryValue::Long(12),
+                Unit::Events,
+                Variability::Monotonic,
+            )
+    }
+
+    /// The names and flags of the entries, read from their headers.
+    fn flags_of_the_entries(bytes: &[u8], endianness: Endianness) -> Vec<(String, u8)> {
+        let int = |at: usize| {
+            let field = bytes[at..at + 4].try_into().unwrap();
+            match endianness {
+                Endianness::BigEndian => i32::from_be_bytes(field),
+                Endianness::LittleEndian => i32::from_le_bytes(field),
+            }
+        };
+        let mut start = int(24) as usize;
+        (0..int(28))
+            .map(|_| {
+                let name_start = start + int(start + 4) as usize;
+                let name_length = bytes[name_start..].iter().position(|b| *b == 0).unwrap();
+                let name = String::from_utf8(bytes[name_start..name_start + name_length].to_vec());
+                let flags = bytes[start + 13];
+                start += int(start) as usize;
+                (name.unwrap(), flags)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn build_pads_the_region_up_to_the_capacity() {
+        let builder = given_a_builder(Endianness::LittleEndian);
+        let used = builder.build().unwrap().len();
+
+        let bytes = builder.capacity(4096).build().unwrap();
+
+        assert_eq!(4096, bytes.len());
+        assert!(bytes[used..].iter().all(|byte| *byte == 0));
+        assert_eq!(used, Snapshot::from_bytes(&bytes).unwrap().as_bytes().len());
+    }
+
+    #[test]
+    fn build_ignores_a_capacity_smaller_than_the_entries() {
+        let builder = given_a_builder(Endianness::LittleEndian);
+        let used = builder.build().unwrap().len();
+
+        assert_eq!(used, builder.capacity(8).build().unwrap().len());
+    }
+
+    #[test]
+    fn build_refuses_entries_without_a_value() {
+        let builder = given_a_builder(Endianness::LittleEndian).entry(
+            "sun.missing",
+            EntryValue::NotSet,
+            Unit::None,
+            Variability::Constant,
+        );
+
+        assert!(matches!(builder.build(), Err(Error::ValueDoesNotFit)));
+    }
+
+    #[test]
+    fn written_files_are_monitored() {
+        let directory = temp_dir().join(format!("hsperf_builder_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join(std::process::id().to_string());
+        given_a_builder(Endianness::native())
+            .capacity(32 * 1024)
+            .write_to(fs::File::create(&path).unwrap())
+            .unwrap();
+
+        let monitor = JavaVirtualMachine::from_path(path)
+            .unwrap()
+            .file_checks(FileChecks::Skipped)
+            .monitor();
+
+        fs::remove_dir_all(&directory).unwrap();
+        let monitor = monitor.unwrap();
+        assert!(matches!(
+            monitor.entries()["sun.gc.collector.0.invocations"].value(),
+            Ok(EntryValue::Long(12))
+        ));
+    }
+
+    #[proptest(cases = 64)]
+    fn large_regions_round_trip_through_the_parser(
+        #[strategy(btree_map(
+            "(java\\.|com\\.sun\\.|sun\\.)?[a-z]{1,12}(\\.[a-z]{1,12}){0,4}",
+            any::<i64>(),
+            0..512,
+        ))]
+        counters: std::collections::BTreeMap<String, i64>,
+        big_endian: bool,
+    ) {
+        let endianness = if big_endian {
+            Endianness::BigEndian
+        } else {
+            Endianness::LittleEndian
+        };
+        let bytes = counters
+            .iter()
+            .fold(
+                PerfDataBuilder::new(endianness),
+                |builder, (name, value)| {
+                    builder.entry(
+                        name,
+                        EntryValue::Long(*value),
+                        Unit::Ticks,
+                        Variability::Variable,
+                    )
+                },
+            )
+            .build()
+            .unwrap();
+
+        let snapshot = Snapshot::from_bytes(&bytes).unwrap();
+
+        assert_eq!(counters.len(), snapshot.entries().len());
+        for (name, flags) in flags_of_the_entries(&bytes, endianness) {
+            let supported = name.starts_with("java.") || name.starts_with("com.sun.");
+            assert_eq!(u8::from(supported), flags, "flags of {name}");
+        }
+        for (name, value) in counters {
+            assert!(matches!(
+                snapshot.entries()[&name].value(),
+                Ok(EntryValue::Long(read)) if read == value
+            ));
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{errors::Error, snapshot::Snapshot};
+
+/// Where the bytes of an hsperfdata region come from.
+///
+/// Monitors and their variable entries read their values from a source, so they work the
+/// same over the file mapped by a JVM, bytes received from elsewhere or a recording.
+pub trait PerfDataSource: Send + Sync {
+    /// Number of bytes of the region.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Copies the bytes of the region starting at offset into the buffer.
+    ///
+    /// Fails with `Error::WontBeAbleToRead` when they do not all lie in the region.
+    fn read_at(&self, offset: usize, buffer: &mut [u8]) -> Result<(), Error>;
+
+    /// Moves on to the latest bytes, for the sources that do not follow them on their own.
+    fn refresh(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Owned bytes, which never change.
+impl PerfDataSource for Vec<u8> {
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    fn read_at(&self, offset: usize, buffer: &mut [u8]) -> Result<(), Error> {
+        let bytes = offset
+            .checked_add(buffer.len())
+            .and_then(|end| self.get(offset..end))
+            .ok_or(Error::WontBeAbleToRead)?;
+        buffer.copy_from_slice(bytes);
+        Ok(())
+    }
+}
+
+/// Recorded regions of a JVM, played back one after the other.
+///
+/// The source starts on the first region and moves on to the next one on each refresh,
+/// staying on the last one once all have been played. All the regions are expected to
+/// hold the same entries, as they would when recorded from the same JVM.
+pub struct ReplaySource {
+    regions: Vec<Vec<u8>>,
+    position: AtomicUsize,
+}
+
+impl ReplaySource {
+    pub fn new(regions: Vec<Vec<u8>>) -> Self {
+        Self {
+            regions,
+            position: AtomicUsize::new(0),
+        }
+    }
+
+    /// Plays back the bytes of the snapshots.
+    pub fn from_snapshots<I>(snapshots: I) -> Self
+    where
+        I: IntoIterator<Item = Snapshot>,
+    {
+        Self::new(
+            snapshots
+                .into_iter()
+                .map(|snapshot| snapshot.as_bytes().to_vec())
+                .collect(),
+        )
+    }
+
+    /// Index of the region being played.
+    pub fn position(&self) -> usize {
+        self.position.load(Ordering::Acquire)
+    }
+
+    fn current(&self) -> Option<&Vec<u8>> {
+        self.regions.get(self.position())
+    }
+}
+
+impl PerfDataSource for ReplaySource {
+    fn len(&self) -> usize {
+        self.current().map_or(0, PerfDataSource::len)
+    }
+
+    fn read_at(&self, offset: usize, buffer: &mut [u8]) -> Result<(), Error> {
+        self.current()
+            .ok_or(Error::WontBeAbleToRead)?
+            .read_at(offset, buffer)
+    }
+
+    fn refresh(&self) -> Result<(), Error> {
+        let last = self.regions.len().saturating_sub(1);
+        let _ = self
+            .position
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |position| {
+                (position < last).then_some(position + 1)
+            });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, PerfDataSource, ReplaySource, Snapshot, snapshot::tests::given_a_region};
+
+    #[test]
+    fn bytes_are_read_at_the_offset() {
+        let bytes = vec![1u8, 2, 3, 4, 5];
+        let mut buffer = [0u8; 2];
+
+        bytes.read_at(3, &mut buffer).unwrap();
+
+        assert_eq!([4, 5], buffer);
+    }
+
+    #[test]
+    fn bytes_are_not_read_past_the_end() {
+        let bytes = vec![1u8, 2, 3, 4, 5];
+
+        assert!(matches!(
+            bytes.read_at(4, &mut [0u8; 2]),
+            Err(Error::WontBeAbleToRead)
+        ));
+        assert!(matches!(
+            bytes.read_at(usize::MAX, &mut [0u8; 2]),
+            Err(Error::WontBeAbleToRead)
+        ));
+    }
+
+    #[test]
+    fn replay_moves_to_the_next_region_on_refresh() {
+        let source = ReplaySource::new(vec![vec![1u8], vec![2u8, 2]]);
+        let mut buffer = [0u8; 1];
+
+        source.read_at(0, &mut buffer).unwrap();
+        assert_eq!([1], buffer);
+        assert_eq!(1, source.len());
+
+        source.refresh().unwrap();
+        source.read_at(0, &mut buffer).unwrap();
+        assert_eq!([2], buffer);
+        assert_eq!(2, source.len());
+    }
+
+    #[test]
+    fn replay_stays_on_the_last_region() {
+        let source = ReplaySource::new(vec![vec![1u8], vec![2u8]]);
+
+        for _ in 0..3 {
+            source.refresh().unwrap();
+        }
+
+        assert_eq!(1, source.position());
+    }
+
+    #[test]
+    fn replay_of_nothing_is_empty() {
+        let source = ReplaySource::new(vec![]);
+
+        source.refresh().unwrap();
+
+        assert!(source.is_empty());
+        assert!(matches!(
+            source.read_at(0, &mut [0u8; 1]),
+            Err(Error::WontBeAbleToRead)
+        ));
+    }
+
+    #[test]
+    fn replay_plays_the_bytes_of_the_snapshots() {
+        let snapshots =
+            [1234, 5678].map(|value| Snapshot::from_bytes(&given_a_region(value)).unwrap());
+
+        let source = ReplaySource::from_snapshots(snapshots);
+
+        assert_eq!(given_a_region(1234).len(), source.len());
+        source.refresh().unwrap();
+        let mut buffer = [0u8; 8];
+        source.read_at(56, &mut buffer).unwrap();
+        assert_eq!(5678i64.to_ne_bytes(), buffer);
+    }
+}
@@ -0,0 +1,512 @@
+use std::{
+    collections::HashMap,
+    env::temp_dir,
+    fs::{self, DirBuilder, OpenOptions},
+    io::ErrorKind,
+    os::unix::fs::{DirBuilderExt, OpenOptionsExt},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use nix::{libc, unistd::Uid};
+
+use crate::{
+    EntryValue, PerfDataSource,
+    data_pointer::DataPointer,
+    errors::Error,
+    file_checks::FileChecks,
+    jvm_status::JvmStatus,
+    memory_region::WritableRegion,
+    perf_data::{Endianness, RegionEncoder, Unit, Variability},
+};
+
+const ACCESSIBLE_OFFSET: usize = 7;
+
+/// Writer of the hsperfdata file of the current process, so that the JDK tools and the
+/// monitors of this crate see its metrics as they would the ones of a JVM.
+///
+/// ```no_run
+/// use hsperf::{EntryValue, PerfDataWriter, Unit, Variability};
+///
+/// let published = PerfDataWriter::new()
+///     .constant("sun.rt.javaCommand", EntryValue::String("sidecar".to_string()), Unit::String)
+///     .counter("sidecar.requests", Unit::Events, Variability::Monotonic)
+///     .publish()?;
+///
+/// let requests = published.counter("sidecar.requests").unwrap();
+/// requests.add(1)?;
+/// # Ok::<(), hsperf::Error>(())
+/// ```
+pub struct PerfDataWriter {
+    encoder: RegionEncoder,
+    variables: Vec<String>,
+    capacity: usize,
+    remove_stale_files: bool,
+}
+
+impl Default for PerfDataWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PerfDataWriter {
+    /// A writer without entries, with the 64 KiB capacity of the files published by default
+    /// by the JVMs.
+    pub fn new() -> Self {
+        Self {
+            encoder: RegionEncoder::new(Endianness::native()),
+            variables: vec![],
+            capacity: 64 * 1024,
+            remove_stale_files: false,
+        }
+    }
+
+    /// Refines the file with an entry whose value never changes.
+    pub fn constant(mut self, name: &str, value: EntryValue, unit: Unit) -> Self {
+        self.encoder.add(name, value, unit, Variability::Constant);
+        self.variables.retain(|variable| variable != name);
+        self
+    }
+
+    /// Refines the file with a Long counter starting at 0, updated through `Counter`.
+    pub fn counter(mut self, name: &str, unit: Unit, variability: Variability) -> Self {
+        self.encoder
+            .add(name, EntryValue::Long(0), unit, variability);
+        self.variables.push(name.to_string());
+        self
+    }
+
+    /// Refines the file with a string entry, updated through `StringVariable`. It has room
+    /// for 255 bytes, or for its initial value when it is longer.
+    pub fn string(mut self, name: &str, value: &str, variability: Variability) -> Self {
+        self.encoder.add(
+            name,
+            EntryValue::String(value.to_string()),
+            Unit::String,
+            variability,
+        );
+        self.variables.push(name.to_string());
+        self
+    }
+
+    /// Refines the size of the file, which is never smaller than its entries.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Refines the publication so that it also removes the files left in
+    /// /tmp/hsperfdata_<user> by processes that are no longer running, as HotSpot does.
+    /// Only `publish` removes them, `publish_to` never removes other files.
+    pub fn remove_stale_files(mut self) -> Self {
+        self.remove_stale_files = true;
+        self
+    }
+
+    /// Publishes the file in /tmp/hsperfdata_<user>/<pid>, where the JDK tools look for it.
+    pub fn publish(self) -> Result<PublishedPerfData, Error> {
+        let directory = temp_dir().join(format!("hsperfdata_{}", whoami::username()));
+        let remove_stale_files = self.remove_stale_files;
+        let published = self.publish_to(&directory.join(std::process::id().to_string()))?;
+        if remove_stale_files {
+            remove_files_of_exited_processes(&directory);
+        }
+        Ok(published)
+    }
+
+    /// Publishes the file at the path, creating its directory if needed. Like HotSpot, the
+    /// directory must be owned by the current user and not be world-writable.
+    pub fn publish_to(self, path: &Path) -> Result<PublishedPerfData, Error> {
+        if let Some(directory) = path.parent() {
+            if !directory.exists() {
+                DirBuilder::new()
+                    .mode(0o755)
+                    .recursive(true)
+                    .create(directory)
+                    .map_err(Error::FailedToWrite)?;
+            }
+            FileChecks::Enforced.check_directory(directory, Uid::effective().as_raw())?;
+        }
+        let (mut bytes, pointers) = self.encoder.encode_with_pointers()?;
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .custom_flags(libc::O_NOFOLLOW)
+            .open(path)
+            .map_err(|e| match e.raw_os_error() {
+                Some(libc::ELOOP) => Error::InsecureFile {
+                    path: path.to_path_buf(),
+                    reason: "is a symlink",
+                },
+                _ => Error::FailedToOpen(e),
+            })?;
+        let length = self.capacity.max(bytes.len());
+        file.set_len(length as u64).map_err(Error::FailedToWrite)?;
+        let region = Arc::new(WritableRegion::map(file, length)?);
+        let published = PublishedPerfData {
+            path: path.to_path_buf(),
+            region,
+            variables: pointers
+                .into_iter()
+                .filter(|(name, _)| self.variables.contains(name))
+                .collect(),
+            removed: false,
+        };
+        // The region is only flagged accessible once its entries are all written.
+        bytes[ACCESSIBLE_OFFSET] = 0;
+        published.region.write_at(0, &bytes)?;
+        published.region.write_at(ACCESSIBLE_OFFSET, &[1])?;
+        Ok(published)
+    }
+}
+
+/// Published hsperfdata file, removed when unpublished or dropped.
+///
+/// The file is left behind when the process exits without dropping it, through
+/// `std::process::exit`, a signal or a publication kept in a static, as the ones of killed
+/// JVMs are. It is then removed by the next publication removing stale files.
+pub struct PublishedPerfData {
+    path: PathBuf,
+    region: Arc<WritableRegion>,
+    variables: HashMap<String, DataPointer>,
+    removed: bool,
+}
+
+impl PublishedPerfData {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The counter with the name, if it was registered as one.
+    pub fn counter(&self, name: &str) -> Option<Counter> {
+        self.variables
+            .get(name)
+            .filter(|pointer| pointer.vector_length().is_none())
+            .map(|pointer| Counter {
+                region: self.region.clone(),
+                offset: pointer.offset(),
+            })
+    }
+
+    /// The string with the name, if it was registered as one.
+    pub fn string(&self, name: &str) -> Option<StringVariable> {
+        self.variables.get(name).and_then(|pointer| {
+            pointer.vector_length().map(|capacity| StringVariable {
+                region: self.region.clone(),
+                offset: pointer.offset(),
+                capacity,
+            })
+        })
+    }
+
+    /// Removes the file, reporting the failures that dropping the publication ignores. A
+    /// file already removed, e.g. by a cleaner of the temporary directory, is not one.
+    pub fn unpublish(mut self) -> Result<(), Error> {
+        self.remove()
+    }
+
+    fn remove(&mut self) -> Result<(), Error> {
+        if self.removed {
+            return Ok(());
+        }
+        self.removed = true;
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(Error::FailedToWrite(e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Drop for PublishedPerfData {
+    fn drop(&mut self) {
+        // Dropping cannot report a failure, which unpublish does.
+        let _ = self.remove();
+    }
+}
+
+/// Removes the files of the hsperfdata directory named after the pid of a process that is
+/// no longer running. The files that cannot be removed are left for the next publication.
+fn remove_files_of_exited_processes(directory: &Path) {
+    let Ok(files) = fs::read_dir(directory) else {
+        return;
+    };
+    for file in files.flatten() {
+        // Only the names that can be pids are, as any other process is reported exited.
+        let stale = file
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<i32>().ok())
+            .is_some_and(|pid| pid > 0 && !JvmStatus::is_alive(pid as u32));
+        if stale && file.file_type().is_ok_and(|file_type| file_type.is_file()) {
+            let _ = fs::remove_file(file.path());
+        }
+    }
+}
+
+/// Long entry of a published file, updated without locks from any thread.
+#[derive(Clone)]
+pub struct Counter {
+    region: Arc<WritableRegion>,
+    offset: usize,
+}
+
+impl Counter {
+    pub fn get(&self) -> Result<i64, Error> {
+        let mut buffer = [0u8; size_of::<i64>()];
+        self.region.read_at(self.offset, &mut buffer)?;
+        Ok(i64::from_ne_bytes(buffer))
+    }
+
+    pub fn set(&self, value: i64) -> Result<(), Error> {
+        self.region.write_at(self.offset, &value.to_ne_bytes())
+    }
+
+    /// Only available on the targets with 64 bits atomics.
+    #[cfg(target_has_atomic = "64")]
+    pub fn add(&self, delta: i64) -> Result<(), Error> {
+        self.region.add_i64(self.offset, delta)
+    }
+}
+
+/// String entry of a published file. Its bytes are written one by one, so readers may see
+/// a torn value, which `ReadMode::Consistent` makes unlikely.
+#[derive(Clone)]
+pub struct StringVariable {
+    region: Arc<WritableRegion>,
+    offset: usize,
+    capacity: usize,
+}
+
+impl StringVariable {
+    /// Sets the value, written in Latin-1. Fails with `Error::ValueDoesNotFit` when it does
+    /// not fit the entry along with its terminating NUL.
+    pub fn set(&self, value: &str) -> Result<(), Error> {
+        let mut bytes = RegionEncoder::latin1(value);
+        if bytes.len() >= self.capacity {
+            return Err(Error::ValueDoesNotFit);
+        }
+        bytes.resize(self.capacity, 0);
+        self.region.write_at(self.offset, &bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env::temp_dir,
+        fs::{self, Permissions},
+        os::unix::fs::PermissionsExt,
+        path::{Path, PathBuf},
+        process::Command,
+    };
+
+    use crate::{
+        EntryValue, Error, JavaVirtualMachine, JvmMonitor, PerfDataWriter, Unit, Variability,
+        perf_data_writer::{PublishedPerfData, remove_files_of_exited_processes},
+    };
+
+    const REQUESTS: &str = "sidecar.requests";
+    const STATE: &str = "sidecar.state";
+
+    fn given_a_path(name: &str) -> PathBuf {
+        let directory = temp_dir().join(format!("hsperf_writer_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        directory.join(std::process::id().to_string())
+    }
+
+    fn given_a_publication(name: &str) -> PublishedPerfData {
+        PerfDataWriter::new()
+            .constant(
+                "sun.rt.javaCommand",
+                EntryValue::String("sidecar".to_string()),
+                Unit::String,
+            )
+            .counter(REQUESTS, Unit::Events, Variability::Monotonic)
+            .string(STATE, "starting", Variability::Variable)
+            .publish_to(&given_a_path(name))
+            .unwrap()
+    }
+
+    fn given_a_monitor(published: &PublishedPerfData) -> JvmMonitor {
+        JavaVirtualMachine::from_path(published.path().to_path_buf())
+            .unwrap()
+            .monitor()
+            .unwrap()
+    }
+
+    #[test]
+    fn published_files_pass_the_checks_and_are_monitored() {
+        let published = given_a_publication("monitored");
+
+        let monitor = given_a_monitor(&published);
+
+        assert_eq!(3, monitor.entries().len());
+        assert_eq!(
+            0o600,
+            fs::metadata(published.path()).unwrap().permissions().mode() & 0o777
+        );
+        assert!(matches!(
+            monitor.entries()["sun.rt.javaCommand"].value(),
+            Ok(EntryValue::String(command)) if command == "sidecar"
+        ));
+    }
+
+    #[test]
+    fn monitors_see_the_updates() {
+        let published = given_a_publication("updates");
+        let monitor = given_a_monitor(&published);
+        let requests = published.counter(REQUESTS).unwrap();
+
+        requests.set(40).unwrap();
+        requests.add(2).unwrap();
+        published.string(STATE).unwrap().set("ready").unwrap();
+
+        assert_eq!(42, requests.get().unwrap());
+        assert!(matches!(
+            monitor.entries()[REQUESTS].value(),
+            Ok(EntryValue::Long(42))
+        ));
+        assert!(matches!(
+            monitor.entries()[STATE].value(),
+            Ok(EntryValue::String(state)) if state == "ready"
+        ));
+    }
+
+    #[test]
+    fn counters_are_updated_from_many_threads() {
+        let published = given_a_publication("threads");
+        let requests = published.counter(REQUESTS).unwrap();
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                let requests = requests.clone();
+                scope.spawn(move || {
+                    for _ in 0..1000 {
+                        requests.add(1).unwrap();
+                    }
+                });
+            }
+        });
+
+        assert_eq!(4000, requests.get().unwrap());
+    }
+
+    #[test]
+    fn only_variables_are_updated() {
+        let published = given_a_publication("variables");
+
+        assert!(published.counter("sun.rt.javaCommand").is_none());
+        assert!(published.counter(STATE).is_none());
+        assert!(published.string(REQUESTS).is_none());
+        assert!(matches!(
+            published.string(STATE).unwrap().set(&"x".repeat(256)),
+            Err(Error::ValueDoesNotFit)
+        ));
+    }
+
+    #[test]
+    fn dropped_files_are_removed() {
+        let published = given_a_publication("dropped");
+        let path = published.path().to_path_buf();
+
+        drop(published);
+
+        assert!(!path.exists());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn unpublished_files_are_removed() {
+        let published = given_a_publication("unpublished");
+        let path = published.path().to_path_buf();
+
+        published.unpublish().unwrap();
+
+        assert!(!path.exists());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn unpublish_ignores_files_already_removed() {
+        let published = given_a_publication("cleaned");
+        fs::remove_file(published.path()).unwrap();
+        let directory = published.path().parent().unwrap().to_path_buf();
+
+        let result = published.unpublish();
+
+        fs::remove_dir_all(directory).unwrap();
+        assert!(result.is_ok());
+    }
+
+    /// Lays out the files of an exited process, of a running one and two other files in
+    /// the directory of the path.
+    fn given_files_of_processes(path: &Path) -> [PathBuf; 4] {
+        let directory = path.parent().unwrap();
+        fs::create_dir_all(directory).unwrap();
+        let mut exited = Command::new("true").spawn().unwrap();
+        exited.wait().unwrap();
+        let running = std::os::unix::process::parent_id().to_string();
+        let files = [
+            &exited.id().to_string(),
+            &running,
+            "4294967295",
+            "not_a_pid",
+        ]
+        .map(|name| directory.join(name));
+        for file in &files {
+            fs::write(file, [0u8; 4]).unwrap();
+        }
+        files
+    }
+
+    #[test]
+    fn files_of_exited_processes_are_removed() {
+        let path = given_a_path("stale");
+        let files = given_files_of_processes(&path);
+        let directory = path.parent().unwrap();
+
+        remove_files_of_exited_processes(directory);
+
+        let exists = files.each_ref().map(|file| file.exists());
+        fs::remove_dir_all(directory).unwrap();
+        assert_eq!([false, true, true, true], exists);
+    }
+
+    #[test]
+    fn publish_to_leaves_the_other_files() {
+        let path = given_a_path("others");
+        let files = given_files_of_processes(&path);
+
+        let published = PerfDataWriter::new()
+            .remove_stale_files()
+            .publish_to(&path)
+            .unwrap();
+
+        assert!(files.iter().all(|file| file.exists()));
+        drop(published);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn publish_refuses_a_world_writable_directory() {
+        let path = given_a_path("writable");
+        let directory = path.parent().unwrap();
+        fs::create_dir_all(directory).unwrap();
+        fs::set_permissions(directory, Permissions::from_mode(0o777)).unwrap();
+
+        let result = PerfDataWriter::new().publish_to(&path);
+
+        fs::remove_dir_all(directory).unwrap();
+        assert!(matches!(
+            result,
+            Err(Error::InsecureFile {
+                reason: "is world-writable",
+                ..
+            })
+        ));
+    }
+}
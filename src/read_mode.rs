@@ -0,0 +1,134 @@
+use std::thread;
+
+use crate::{EntryValue, PerfDataSource, data_pointer::DataPointer, errors::Error};
+
+/// Number of times a consistent read is attempted before giving up.
+const MAX_ATTEMPTS: usize = 16;
+
+/// How the values of variable entries are read while the JVM may be updating them.
+///
+/// On 64 bits targets, aligned 8 bytes scalars are loaded at once, from a mapped region as well
+/// as when it is copied into a snapshot, so they are never torn. On 32 bits targets they are
+/// read byte by byte, like vectors and strings, which span several words that the JVM updates
+/// one after the other.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ReadMode {
+    /// Values are read once. A vector or a string may mix two updates.
+    #[default]
+    Direct,
+    /// The bytes of a value are copied twice, a moment apart, and the copy is retried until
+    /// both match. The value is then decoded from the copy.
+    ///
+    /// This catches the updates made while a value is copied, but not a writer stalled in
+    /// the middle of an update for as long as both copies take, which leaves them torn
+    /// the same way. Torn values are unlikely, not impossible.
+    Consistent,
+}
+
+impl ReadMode {
+    pub(crate) fn read(
+        &self,
+        data_pointer: &DataPointer,
+        region: &dyn PerfDataSource,
+    ) -> Result<EntryValue, Error> {
+        match self {
+            ReadMode::Direct => data_pointer.read_value(region),
+            ReadMode::Consistent => {
+                for _ in 0..MAX_ATTEMPTS {
+                    let first = data_pointer.read_bytes(region)?;
+                    // Gives an update in progress the time to complete, so that it shows in the second copy.
+                    thread::yield_now();
+                    if data_pointer.read_bytes(region)? == first {
+                        return data_pointer.at(0).read_value(&first);
+                    }
+                }
+                Err(Error::InconsistentRead)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use crate::{
+        EntryValue, PerfDataSource,
+        data_pointer::DataPointer,
+        errors::Error,
+        perf_data::{DataType, Endianness},
+        read_mode::{MAX_ATTEMPTS, ReadMode},
+    };
+
+    const CAUSES: [&[u8; 16]; 2] = [b"System.gc()\0\0\0\0\0", b"Allocation Fail\0"];
+
+    fn given_a_pointer() -> DataPointer {
+        DataPointer::new_vector(DataType::Byte, 16, 0, Endianness::LittleEndian)
+    }
+
+    #[test]
+    fn default_is_direct() {
+        assert_eq!(ReadMode::Direct, ReadMode::default());
+    }
+
+    #[test]
+    fn consistent_read_returns_a_stable_value() {
+        let region = CAUSES[0].to_vec();
+
+        let value = ReadMode::Consistent
+            .read(&given_a_pointer(), &region)
+            .unwrap();
+
+        assert!(matches!(value, EntryValue::String(cause) if cause == "System.gc()"));
+    }
+
+    /// Region holding a different value on each read, in the order scripted, then the
+    /// last one.
+    struct ScriptedRegion(Mutex<Vec<&'static [u8; 16]>>);
+
+    impl PerfDataSource for ScriptedRegion {
+        fn len(&self) -> usize {
+            16
+        }
+
+        fn read_at(&self, offset: usize, buffer: &mut [u8]) -> Result<(), Error> {
+            let mut values = self.0.lock().unwrap();
+            let value = if values.len() > 1 {
+                values.remove(0)
+            } else {
+                values[0]
+            };
+            value.to_vec().read_at(offset, buffer)
+        }
+    }
+
+    #[test]
+    fn consistent_read_retries_the_values_changing_while_read() {
+        // The first half of an update over the second half of the previous value.
+        let torn = b"Allocatic()\0\0\0\0\0";
+        let region = ScriptedRegion(Mutex::new(vec![torn, CAUSES[1]]));
+
+        let value = ReadMode::Consistent
+            .read(&given_a_pointer(), &region)
+            .unwrap();
+
+        assert!(matches!(value, EntryValue::String(cause) if cause == "Allocation Fail"));
+    }
+
+    #[test]
+    fn consistent_read_gives_up_on_values_always_changing() {
+        let region = ScriptedRegion(Mutex::new(
+            CAUSES
+                .iter()
+                .cycle()
+                .take(2 * MAX_ATTEMPTS + 1)
+                .copied()
+                .collect(),
+        ));
+
+        assert!(matches!(
+            ReadMode::Consistent.read(&given_a_pointer(), &region),
+            Err(Error::InconsistentRead)
+        ));
+    }
+}
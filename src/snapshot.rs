@@ -0,0 +1,419 @@
+use std::{collections::HashMap, io::Read, sync::Arc};
+
+use crate::{
+    PerfDataSource,
+    errors::Error,
+    jvm_monitor::Entry,
+    parsing::{Diagnostic, ParsingMode},
+    perf_data::PerfDataProlog,
+};
+
+/// Immutable copy of the hsperfdata region of a JVM taken at a given point in time.
+///
+/// All the entries of a snapshot read from the copied bytes, so their values stay
+/// consistent with each other however many times they are read.
+#[derive(Clone)]
+pub struct Snapshot {
+    pid: u32,
+    bytes: Arc<Vec<u8>>,
+    entries: HashMap<String, Entry>,
+    diagnostics: Arc<Vec<Diagnostic>>,
+}
+
+impl Snapshot {
+    /// Copies the bytes used in the region.
+    ///
+    /// The JVM may use more bytes while they are copied, so the copy is taken again until
+    /// the prolog it holds agrees with the bytes copied.
+    pub(crate) fn copy(
+        pid: u32,
+        region: &dyn PerfDataSource,
+        parsing_mode: ParsingMode,
+    ) -> Result<Self, Error> {
+        let mut used = PerfDataProlog::used_bytes(region, region.len())?;
+        let bytes = loop {
+            let mut bytes = vec![0u8; used];
+            region.read_at(0, &mut bytes)?;
+            // Used bytes only grow, up to the length of the region.
+            let copied_used = PerfDataProlog::used_bytes(&bytes, region.len())?;
+            if copied_used <= used {
+                bytes.truncate(copied_used);
+                break Arc::new(bytes);
+            }
+            used = copied_used;
+        };
+        let (entries, diagnostics) = PerfDataProlog::read_entries(
+            &(bytes.clone() as Arc<dyn PerfDataSource>),
+            parsing_mode,
+        )?;
+        Ok(Self {
+            pid,
+            bytes,
+            entries,
+            diagnostics: Arc::new(diagnostics),
+        })
+    }
+
+    /// Decodes the bytes of an hsperfdata region, such as the bytes of a file or of
+    /// `as_bytes`, whatever the byte order of the machine they come from.
+    ///
+    /// The pid is not recorded in the region, so it is 0.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Self::from_bytes_with_parsing_mode(bytes, ParsingMode::Strict)
+    }
+
+    /// Decodes the bytes of an hsperfdata region, handling the entries it cannot decode
+    /// according to the parsing mode.
+    pub fn from_bytes_with_parsing_mode(
+        bytes: &[u8],
+        parsing_mode: ParsingMode,
+    ) -> Result<Self, Error> {
+        Self::copy(0, &bytes.to_vec(), parsing_mode)
+    }
+
+    /// Reads the reader to its end and decodes its bytes as an hsperfdata region.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self, Error> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .map_err(Error::FailedToRead)?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// the pid of the vm the snapshot was taken from
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Returns the hsperfdata entries as they were when the snapshot was taken
+    pub fn entries(&self) -> &HashMap<String, Entry> {
+        &self.entries
+    }
+
+    /// The entries skipped because they could not be decoded, in lenient parsing mode
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Refines the snapshot so that it only keep the entries matching the filter.
+    pub fn only<P>(mut self, mut filter: P) -> Self
+    where
+        P: FnMut(&str) -> bool,
+    {
+        self.entries.retain(|key, _value| filter(key));
+        self
+    }
+
+    /// The raw bytes of the copied region, laid out as in the hsperfdata file.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl PartialEq for Snapshot {
+    fn eq(&self, other: &Self) -> bool {
+        self.pid == other.pid && self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl Eq for Snapshot {}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use parameterized::parameterized;
+
+    use crate::{
+        Endianness, Entry, EntryValue, Error, PerfDataBuilder, PerfDataSource, Unit, Variability,
+        parsing::ParsingMode, snapshot::Snapshot,
+    };
+
+    const PROLOG_SIZE: usize = 32;
+    const ENTRY_SIZE: usize = 32;
+
+    /// Lays out a region holding a single monotonic long entry named t.c.
+    pub(crate) fn given_a_region(value: i64) -> Vec<u8> {
+        given_a_region_in(Endianness::native(), value)
+    }
+
+    /// Lays out the region of given_a_region in the byte order. The value of the counter
+    /// is at bytes 56..64.
+    fn given_a_region_in(endianness: Endianness, value: i64) -> Vec<u8> {
+        PerfDataBuilder::new(endianness)
+            .entry(
+                "t.c",
+                EntryValue::Long(value),
+                Unit::Events,
+                Variability::Monotonic,
+            )
+            .build()
+            .unwrap()
+    }
+
+    fn take_snapshot(region: &[u8]) -> Snapshot {
+        Snapshot::copy(12, &region.to_vec(), ParsingMode::Strict).unwrap()
+    }
+
+    fn counter_value(snapshot: &Snapshot) -> i64 {
+        match snapshot.entries()["t.c"].value().unwrap() {
+            EntryValue::Long(value) => value,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn copy_reads_the_entries_of_the_region() {
+        let region = given_a_region(1234);
+
+        let snapshot = take_snapshot(&region);
+
+        assert_eq!(12, snapshot.pid());
+        assert_eq!(1, snapshot.entries().len());
+        assert_eq!(1234, counter_value(&snapshot));
+    }
+
+    /// Region of two entries whose prolog only tells about the first until more than the
+    /// prolog is read, as when the JVM adds an entry while a snapshot is taken.
+    struct GrowingRegion {
+        before: Vec<u8>,
+        after: Vec<u8>,
+        grown: AtomicBool,
+    }
+
+    impl GrowingRegion {
+        fn new() -> Self {
+            let after = PerfDataBuilder::new(Endianness::native())
+                .entry(
+                    "t.c",
+                    EntryValue::Long(1234),
+                    Unit::Events,
+                    Variability::Monotonic,
+                )
+                .entry(
+                    "t.d",
+                    EntryValue::Long(5678),
+                    Unit::Events,
+                    Variability::Monotonic,
+                )
+                .build()
+                .unwrap();
+            let mut before = after.clone();
+            before[8..12].copy_from_slice(&((PROLOG_SIZE + ENTRY_SIZE) as i32).to_ne_bytes());
+            before[28..32].copy_from_slice(&1i32.to_ne_bytes());
+            Self {
+                before,
+                after,
+                grown: AtomicBool::new(false),
+            }
+        }
+    }
+
+    impl PerfDataSource for GrowingRegion {
+        fn len(&self) -> usize {
+            self.after.len()
+        }
+
+        fn read_at(&self, offset: usize, buffer: &mut [u8]) -> Result<(), Error> {
+            if offset + buffer.len() > PROLOG_SIZE {
+                self.grown.store(true, Ordering::Release);
+            }
+            if self.grown.load(Ordering::Acquire) {
+                self.after.read_at(offset, buffer)
+            } else {
+                self.before.read_at(offset, buffer)
+            }
+        }
+    }
+
+    #[test]
+    fn copy_follows_the_region_growing_while_copied() {
+        let region = GrowingRegion::new();
+
+        let snapshot = Snapshot::copy(12, &region, ParsingMode::Strict).unwrap();
+
+        assert_eq!(2, snapshot.entries().len());
+        assert_eq!(region.after, snapshot.as_bytes());
+    }
+
+    #[test]
+    fn values_do_not_change_when_the_region_does() {
+        let mut region = given_a_region(1234);
+        let snapshot = take_snapshot(&region);
+
+        region[56..64].copy_from_slice(&5678i64.to_ne_bytes());
+
+        assert_eq!(1234, counter_value(&snapshot));
+    }
+
+    #[test]
+    fn as_bytes_returns_the_used_bytes() {
+        let region = given_a_region(1234);
+
+        let snapshot = take_snapshot(&region);
+
+        assert_eq!(PROLOG_SIZE + ENTRY_SIZE, snapshot.as_bytes().len());
+        assert_eq!(1234i64.to_ne_bytes(), snapshot.as_bytes()[56..64]);
+    }
+
+    #[test]
+    fn snapshots_of_identical_regions_are_equal() {
+        let region = given_a_region(1234);
+
+        assert!(take_snapshot(&region) == take_snapshot(&region));
+        assert!(take_snapshot(&region) != take_snapshot(&given_a_region(5678)));
+    }
+
+    #[test]
+    fn only_keeps_the_matching_entries() {
+        let region = given_a_region(1234);
+
+        let snapshot = take_snapshot(&region).only(|name| name != "t.c");
+
+        assert!(snapshot.entries().is_empty());
+    }
+
+    #[test]
+    fn snapshot_can_be_read_from_another_thread() {
+        let region = given_a_region(1234);
+        let snapshot = take_snapshot(&region);
+
+        let value = std::thread::spawn(move || counter_value(&snapshot))
+            .join()
+            .unwrap();
+
+        assert_eq!(1234, value);
+    }
+
+    #[test]
+    fn variable_entries_outlive_the_snapshot() {
+        let region = given_a_region(1234);
+        let entry = take_snapshot(&region).entries()["t.c"].clone();
+
+        let value = std::thread::spawn(move || match entry {
+            Entry::Variable(entry) => entry.value().unwrap(),
+            Entry::Constant(_) => unreachable!(),
+        })
+        .join()
+        .unwrap();
+
+        assert!(matches!(value, EntryValue::Long(1234)));
+    }
+
+    #[test]
+    fn copy_errors_name_the_failing_entry() {
+        let mut region = given_a_region(1234);
+        // Turn the counter into a constant vector of 100 longs, past the end of the entry.
+        region[PROLOG_SIZE + 8..PROLOG_SIZE + 16].copy_from_slice(&[100, 0, 0, 0, b'J', 0, 4, 1]);
+
+        let error = Snapshot::copy(12, &region, ParsingMode::Strict)
+            .err()
+            .unwrap();
+
+        assert!(
+            matches!(&error, Error::InEntry { index: 0, offset: 32, name: Some(name), source }
+            if name == "t.c" && matches!(**source, Error::InvalidField { field: "data_offset", .. }))
+        );
+    }
+
+    #[test]
+    fn copy_skips_the_failing_entry_in_lenient_mode() {
+        let mut bytes = given_a_region(1234);
+        // Append a copy of the entry named t.d and make the first one unreadable.
+        let entry = bytes[PROLOG_SIZE..].to_vec();
+        bytes.extend(entry);
+        bytes[PROLOG_SIZE + ENTRY_SIZE + 22] = b'd';
+        bytes[8..12].copy_from_slice(&((PROLOG_SIZE + 2 * ENTRY_SIZE) as i32).to_ne_bytes());
+        bytes[28..32].copy_from_slice(&2i32.to_ne_bytes());
+        bytes[PROLOG_SIZE + 8..PROLOG_SIZE + 16].copy_from_slice(&[100, 0, 0, 0, b'J', 0, 4, 1]);
+        let snapshot = Snapshot::copy(12, &bytes, ParsingMode::Lenient).unwrap();
+
+        assert_eq!(1, snapshot.entries().len());
+        assert!(snapshot.entries().contains_key("t.d"));
+        assert_eq!(1, snapshot.diagnostics().len());
+        let diagnostic = &snapshot.diagnostics()[0];
+        assert_eq!(0, diagnostic.index());
+        assert_eq!(PROLOG_SIZE, diagnostic.offset());
+        assert_eq!(Some("t.c"), diagnostic.name());
+        assert!(matches!(
+            diagnostic.reason(),
+            Error::InvalidField {
+                field: "data_offset",
+                ..
+            }
+        ));
+    }
+
+    #[parameterized(endianness = {
+        Endianness::LittleEndian, Endianness::BigEndian
+    })]
+    fn from_bytes_reads_regions_in_either_byte_order(endianness: Endianness) {
+        let bytes = given_a_region_in(endianness, 1234);
+
+        let snapshot = Snapshot::from_bytes(&bytes).unwrap();
+
+        assert_eq!(0, snapshot.pid());
+        assert_eq!(1234, counter_value(&snapshot));
+        assert_eq!(&bytes[..], snapshot.as_bytes());
+    }
+
+    #[test]
+    fn from_bytes_reads_the_bytes_of_another_snapshot() {
+        let snapshot = take_snapshot(&given_a_region(1234));
+
+        let copy = Snapshot::from_bytes(snapshot.as_bytes()).unwrap();
+
+        assert_eq!(1234, counter_value(&copy));
+    }
+
+    #[test]
+    fn from_bytes_refuses_malformed_regions() {
+        let mut bytes = given_a_region(1234);
+        bytes[0] = 0;
+
+        assert!(matches!(
+            Snapshot::from_bytes(&bytes),
+            Err(Error::InvalidMagicNumber(_))
+        ));
+        assert!(matches!(
+            Snapshot::from_bytes(&bytes[..16]),
+            Err(Error::WontBeAbleToRead)
+        ));
+    }
+
+    #[test]
+    fn from_bytes_with_parsing_mode_skips_the_failing_entry_in_lenient_mode() {
+        let mut bytes = given_a_region(1234);
+        bytes[PROLOG_SIZE + 8..PROLOG_SIZE + 16].copy_from_slice(&[100, 0, 0, 0, b'J', 0, 4, 1]);
+
+        let snapshot =
+            Snapshot::from_bytes_with_parsing_mode(&bytes, ParsingMode::Lenient).unwrap();
+
+        assert!(snapshot.entries().is_empty());
+        assert_eq!(1, snapshot.diagnostics().len());
+    }
+
+    #[test]
+    fn from_reader_reads_the_region_to_its_end() {
+        let bytes = given_a_region_in(Endianness::BigEndian, 1234);
+
+        let snapshot = Snapshot::from_reader(&bytes[..]).unwrap();
+
+        assert_eq!(1234, counter_value(&snapshot));
+    }
+
+    #[test]
+    fn from_reader_reports_read_failures() {
+        struct FailingReader;
+        impl std::io::Read for FailingReader {
+            fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::from(std::io::ErrorKind::BrokenPipe))
+            }
+        }
+
+        assert!(matches!(
+            Snapshot::from_reader(FailingReader),
+            Err(Error::FailedToRead(_))
+        ));
+    }
+}
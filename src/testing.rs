@@ -0,0 +1,432 @@
+//! In-memory JVM for the unit tests of the crates using hsperf, enabled by the `testing` feature.
+use std::{
+    mem,
+    sync::{
+        Arc, PoisonError, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use crate::{
+    EntryValue, JvmMonitor, PerfDataSource, Snapshot,
+    errors::Error,
+    parsing::ParsingMode,
+    perf_data::{Endianness, RegionEncoder, Unit, Variability},
+};
+
+type Action = Box<dyn FnMut(&mut FakeJvm) -> Result<(), Error> + Send>;
+
+/// Script run periodically as the time of the fake JVM advances.
+struct Script {
+    period: Duration,
+    next_run: Duration,
+    action: Action,
+}
+
+/// Region published by a fake JVM, shared with its monitors.
+#[derive(Clone)]
+struct PublishedRegion(Arc<RwLock<Vec<u8>>>);
+
+impl PerfDataSource for PublishedRegion {
+    fn len(&self) -> usize {
+        self.0.read().unwrap_or_else(PoisonError::into_inner).len()
+    }
+
+    fn read_at(&self, offset: usize, buffer: &mut [u8]) -> Result<(), Error> {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .read_at(offset, buffer)
+    }
+}
+
+/// JVM publishing its hsperfdata in memory, whose entries are set by the test and
+/// updated by scripts as its time advances.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use hsperf::{EntryValue, Unit, Variability, testing::FakeJvm};
+///
+/// let mut jvm = FakeJvm::new()
+///     .entry("sun.gc.collector.0.invocations", EntryValue::Long(0), Unit::Events, Variability::Monotonic)
+///     .every(Duration::from_secs(2), |jvm| jvm.add("sun.gc.collector.0.invocations", 1));
+/// let monitor = jvm.monitor()?;
+///
+/// jvm.advance(Duration::from_secs(5))?;
+///
+/// assert!(matches!(
+///     monitor.entries()["sun.gc.collector.0.invocations"].value()?,
+///     EntryValue::Long(2)
+/// ));
+/// # Ok::<(), hsperf::Error>(())
+/// ```
+pub struct FakeJvm {
+    pid: u32,
+    encoder: RegionEncoder,
+    region: PublishedRegion,
+    elapsed: Duration,
+    scripts: Vec<Script>,
+    monitored: AtomicBool,
+    late_entries: bool,
+}
+
+impl Default for FakeJvm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FakeJvm {
+    /// A JVM without entries, with the pid of the current process so that its monitors
+    /// report it as running.
+    pub fn new() -> Self {
+        Self {
+            pid: std::process::id(),
+            encoder: RegionEncoder::new(Endianness::native()),
+            region: PublishedRegion(Arc::new(RwLock::new(vec![]))),
+            elapsed: Duration::ZERO,
+            scripts: vec![],
+            monitored: AtomicBool::new(false),
+            late_entries: false,
+        }
+    }
+
+    /// Refines the pid of the JVM. A pid without a running process makes its monitors
+    /// report it as exited.
+    pub fn pid(mut self, pid: u32) -> Self {
+        self.pid = pid;
+        self
+    }
+
+    /// Refines the JVM with an entry, whose type is the one of its value. String entries
+    /// have room for 255 bytes, or for their value when it is longer.
+    ///
+    /// Entries are refused once a monitor was created, as they would move the values the
+    /// monitor reads: they are not added and the JVM then fails with
+    /// `Error::AlreadyMonitored`.
+    pub fn entry(
+        mut self,
+        name: &str,
+        value: EntryValue,
+        unit: Unit,
+        variability: Variability,
+    ) -> Self {
+        if self.monitored.load(Ordering::Acquire) {
+            self.late_entries = true;
+        } else {
+            self.encoder.add(name, value, unit, variability);
+        }
+        self
+    }
+
+    /// Refines the JVM with a script run every period of its time, e.g. to count a young
+    /// collection every 2 seconds. Scripts with a zero period are never run.
+    pub fn every<F>(mut self, period: Duration, action: F) -> Self
+    where
+        F: FnMut(&mut FakeJvm) -> Result<(), Error> + Send + 'static,
+    {
+        if !period.is_zero() {
+            self.scripts.push(Script {
+                period,
+                next_run: self.elapsed + period,
+                action: Box::new(action),
+            });
+        }
+        self
+    }
+
+    /// Time elapsed since the JVM was created.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// The current value of an entry.
+    pub fn value(&self, name: &str) -> Option<&EntryValue> {
+        self.encoder.value(name)
+    }
+
+    /// Changes the value of an entry, which must keep its type. Vectors cannot grow.
+    pub fn set(&mut self, name: &str, value: EntryValue) -> Result<(), Error> {
+        self.refuse_late_entries()?;
+        self.encoder.set(name, value)?;
+        self.publish()
+    }
+
+    /// Adds to the value of an integer entry. The delta and the sum must fit in its type.
+    pub fn add(&mut self, name: &str, delta: i64) -> Result<(), Error> {
+        let value = match *self.value(name).ok_or_else(|| Error::MissingEntry {
+            name: name.to_string(),
+        })? {
+            EntryValue::Long(value) => value.checked_add(delta).map(EntryValue::Long),
+            EntryValue::Int(value) => i32::try_from(delta)
+                .ok()
+                .and_then(|delta| value.checked_add(delta))
+                .map(EntryValue::Int),
+            EntryValue::Short(value) => i16::try_from(delta)
+                .ok()
+                .and_then(|delta| value.checked_add(delta))
+                .map(EntryValue::Short),
+            EntryValue::Byte(value) => i8::try_from(delta)
+                .ok()
+                .and_then(|delta| value.checked_add_signed(delta))
+                .map(EntryValue::Byte),
+            _ => None,
+        };
+        self.set(name, value.ok_or(Error::ValueDoesNotFit)?)
+    }
+
+    /// Moves the time of the JVM forward, running the scripts that fall due in order.
+    pub fn advance(&mut self, by: Duration) -> Result<(), Error> {
+        let until = self.elapsed + by;
+        while let Some(index) = self
+            .scripts
+            .iter()
+            .enumerate()
+            .filter(|(_, script)| script.next_run <= until)
+            .min_by_key(|(_, script)| script.next_run)
+            .map(|(index, _)| index)
+        {
+            let script = &mut self.scripts[index];
+            self.elapsed = script.next_run;
+            script.next_run += script.period;
+            // The script is taken out while it runs, as it changes the JVM.
+            let mut action = mem::replace(&mut self.scripts[index].action, Box::new(|_| Ok(())));
+            let result = action(self);
+            self.scripts[index].action = action;
+            result?;
+        }
+        self.elapsed = until;
+        Ok(())
+    }
+
+    /// Creates a monitor of the JVM, which sees the values as they change.
+    pub fn monitor(&self) -> Result<JvmMonitor, Error> {
+        self.refuse_late_entries()?;
+        self.monitored.store(true, Ordering::Release);
+        self.publish()?;
+        JvmMonitor::from_source(self.pid, self.region.clone())
+    }
+
+    /// Copies the current values of the JVM into a snapshot.
+    pub fn snapshot(&self) -> Result<Snapshot, Error> {
+        self.refuse_late_entries()?;
+        Snapshot::copy(self.pid, &self.encoder.encode()?, ParsingMode::Strict)
+    }
+
+    fn refuse_late_entries(&self) -> Result<(), Error> {
+        if self.late_entries {
+            Err(Error::AlreadyMonitored)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn publish(&self) -> Result<(), Error> {
+        let bytes = self.encoder.encode()?;
+        *self
+            .region
+            .0
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = bytes;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use parameterized::parameterized;
+
+    use crate::{
+        EntryValue, Error, JvmMonitor, JvmStatus, ReadMode, Unit, Variability, testing::FakeJvm,
+    };
+
+    const INVOCATIONS: &str = "sun.gc.collector.0.invocations";
+    const CAUSE: &str = "sun.gc.cause";
+
+    fn given_a_jvm() -> FakeJvm {
+        FakeJvm::new()
+            .entry(
+                "java.property.java.version",
+                EntryValue::String("21.0.2".to_string()),
+                Unit::String,
+                Variability::Constant,
+            )
+            .entry(
+                INVOCATIONS,
+                EntryValue::Long(0),
+                Unit::Events,
+                Variability::Monotonic,
+            )
+            .entry(
+                CAUSE,
+                EntryValue::String("No GC".to_string()),
+                Unit::String,
+                Variability::Variable,
+            )
+    }
+
+    fn invocations(monitor: &JvmMonitor) -> i64 {
+        match monitor.entries()[INVOCATIONS].value().unwrap() {
+            EntryValue::Long(value) => value,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn monitor_reads_the_entries_of_the_jvm() {
+        let monitor = given_a_jvm().monitor().unwrap();
+
+        assert_eq!(3, monitor.entries().len());
+        assert_eq!(JvmStatus::Running, monitor.status());
+        assert!(matches!(
+            monitor.entries()["java.property.java.version"].value(),
+            Ok(EntryValue::String(version)) if version == "21.0.2"
+        ));
+        assert_eq!(Unit::Events, monitor.entries()[INVOCATIONS].unit());
+    }
+
+    #[test]
+    fn monitor_sees_the_values_set() {
+        let mut jvm = given_a_jvm();
+        let monitor = jvm.monitor().unwrap().read_mode(ReadMode::Consistent);
+
+        jvm.set(CAUSE, EntryValue::String("Allocation Failure".to_string()))
+            .unwrap();
+        jvm.add(INVOCATIONS, 3).unwrap();
+
+        assert_eq!(3, invocations(&monitor));
+        assert!(matches!(
+            monitor.entries()[CAUSE].value(),
+            Ok(EntryValue::String(cause)) if cause == "Allocation Failure"
+        ));
+    }
+
+    #[test]
+    fn scripts_run_every_period() {
+        let mut jvm = given_a_jvm()
+            .every(Duration::from_secs(2), |jvm| jvm.add(INVOCATIONS, 1))
+            .every(Duration::ZERO, |jvm| jvm.add(INVOCATIONS, 100));
+        let monitor = jvm.monitor().unwrap();
+
+        jvm.advance(Duration::from_secs(1)).unwrap();
+        assert_eq!(0, invocations(&monitor));
+
+        jvm.advance(Duration::from_secs(6)).unwrap();
+        assert_eq!(3, invocations(&monitor));
+        assert_eq!(Duration::from_secs(7), jvm.elapsed());
+    }
+
+    #[test]
+    fn scripts_run_in_the_order_they_fall_due() {
+        let mut jvm = given_a_jvm()
+            .every(Duration::from_secs(3), |jvm| {
+                jvm.set(CAUSE, EntryValue::String("System.gc()".to_string()))
+            })
+            .every(Duration::from_secs(2), |jvm| {
+                let elapsed = jvm.elapsed().as_secs() as i64;
+                jvm.set(INVOCATIONS, EntryValue::Long(elapsed))
+            });
+
+        jvm.advance(Duration::from_secs(5)).unwrap();
+
+        assert!(matches!(jvm.value(INVOCATIONS), Some(EntryValue::Long(4))));
+        assert!(matches!(
+            jvm.value(CAUSE),
+            Some(EntryValue::String(cause)) if cause == "System.gc()"
+        ));
+    }
+
+    #[test]
+    fn script_errors_stop_the_advance() {
+        let mut jvm = given_a_jvm().every(Duration::from_secs(1), |jvm| jvm.add(CAUSE, 1));
+
+        let result = jvm.advance(Duration::from_secs(3));
+
+        assert!(matches!(result, Err(Error::ValueDoesNotFit)));
+        assert_eq!(Duration::from_secs(1), jvm.elapsed());
+    }
+
+    #[test]
+    fn set_refuses_values_of_another_type() {
+        let mut jvm = given_a_jvm();
+
+        assert!(matches!(
+            jvm.set(INVOCATIONS, EntryValue::Double(1.0)),
+            Err(Error::ValueDoesNotFit)
+        ));
+        assert!(matches!(
+            jvm.add("sun.missing", 1),
+            Err(Error::MissingEntry { name }) if name == "sun.missing"
+        ));
+    }
+
+    #[test]
+    fn snapshot_keeps_the_values_at_the_time() {
+        let mut jvm = given_a_jvm().pid(i32::MAX as u32);
+        let snapshot = jvm.snapshot().unwrap();
+
+        jvm.add(INVOCATIONS, 1).unwrap();
+
+        assert_eq!(i32::MAX as u32, snapshot.pid());
+        assert!(matches!(
+            snapshot.entries()[INVOCATIONS].value(),
+            Ok(EntryValue::Long(0))
+        ));
+        assert_eq!(JvmStatus::Exited, jvm.monitor().unwrap().status());
+    }
+
+    #[parameterized(
+        value = { EntryValue::Long(i64::MAX), EntryValue::Int(0), EntryValue::Short(0), EntryValue::Byte(0) },
+        delta = { 1, 1 << 32, 1 << 16, -1 }
+    )]
+    fn add_refuses_what_does_not_fit(value: EntryValue, delta: i64) {
+        let mut jvm =
+            FakeJvm::new().entry("t.c", value.clone(), Unit::Events, Variability::Monotonic);
+
+        assert!(matches!(jvm.add("t.c", delta), Err(Error::ValueDoesNotFit)));
+        assert_eq!(
+            format!("{value:?}"),
+            format!("{:?}", jvm.value("t.c").unwrap())
+        );
+    }
+
+    #[test]
+    fn add_takes_negative_deltas() {
+        let mut jvm = FakeJvm::new().entry(
+            "t.c",
+            EntryValue::Byte(3),
+            Unit::Events,
+            Variability::Variable,
+        );
+
+        jvm.add("t.c", -2).unwrap();
+
+        assert!(matches!(jvm.value("t.c"), Some(EntryValue::Byte(1))));
+    }
+
+    #[test]
+    fn entries_are_refused_once_monitored() {
+        let jvm = given_a_jvm();
+        let monitor = jvm.monitor().unwrap();
+
+        let mut jvm = jvm.entry(
+            "t.late",
+            EntryValue::Long(0),
+            Unit::Events,
+            Variability::Monotonic,
+        );
+
+        assert!(jvm.value("t.late").is_none());
+        assert!(matches!(
+            jvm.add(INVOCATIONS, 1),
+            Err(Error::AlreadyMonitored)
+        ));
+        assert!(matches!(jvm.monitor(), Err(Error::AlreadyMonitored)));
+        assert!(matches!(jvm.snapshot(), Err(Error::AlreadyMonitored)));
+        assert_eq!(0, invocations(&monitor));
+    }
+}
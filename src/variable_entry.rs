@@ -1,29 +1,59 @@
+use std::{num::NonZeroU64, sync::Arc};
+
 use crate::{
-    EntryValue,
+    EntryValue, PerfDataSource,
     data_pointer::DataPointer,
     errors::Error,
     perf_data::{Unit, Variability},
+    read_mode::ReadMode,
 };
 
 /// Entry read from the Hotspot Data that is characterized as changing in either a Monotonic or Variable way
+///
+/// Cloned entries share the source they read from, which stays alive as long as any of them does.
+#[derive(Clone)]
 pub struct VariableEntry {
+    region: Arc<dyn PerfDataSource>,
     data_pointer: DataPointer,
     variability: Variability,
     unit: Unit,
+    read_mode: ReadMode,
+    frequency: Option<NonZeroU64>,
 }
 
 impl VariableEntry {
-    pub(crate) fn new(data_pointer: DataPointer, variability: Variability, unit: Unit) -> Self {
+    pub(crate) fn new(
+        region: Arc<dyn PerfDataSource>,
+        data_pointer: DataPointer,
+        variability: Variability,
+        unit: Unit,
+    ) -> Self {
         Self {
+            region,
             data_pointer,
             variability,
             unit,
+            read_mode: ReadMode::default(),
+            frequency: None,
         }
     }
 
+    pub(crate) fn set_read_mode(&mut self, read_mode: ReadMode) {
+        self.read_mode = read_mode;
+    }
+
+    pub(crate) fn set_frequency(&mut self, frequency: NonZeroU64) {
+        self.frequency = Some(frequency);
+    }
+
+    pub(crate) fn frequency(&self) -> Option<NonZeroU64> {
+        self.frequency
+    }
+
     /// The value read for the entry.
     pub fn value(&self) -> Result<EntryValue, Error> {
-        self.data_pointer.read_value()
+        self.read_mode
+            .read(&self.data_pointer, self.region.as_ref())
     }
 
     /// The variability of the entry as published by the JVM
@@ -39,61 +69,72 @@ impl VariableEntry {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use crate::{
-        EntryValue,
+        EntryValue, PerfDataSource,
         data_pointer::DataPointer,
-        perf_data::{Endianness, PerfDataEntryHeader, Unit, Variability},
-        safish_pointer::SafishPointer,
+        perf_data::{DataType, Endianness, Unit, Variability},
         variable_entry::VariableEntry,
     };
 
-    fn given_a_pointer(name: &str) -> DataPointer {
-        let value = 123;
-
-        // Fake the pointer
-        DataPointer::Byte(
-            SafishPointer::new(
-                &value,
-                size_of::<PerfDataEntryHeader>() + name.len() + 1,
-                Endianness::BigEndian,
-            )
-            .unwrap(),
-        )
+    fn given_a_region() -> Arc<dyn PerfDataSource> {
+        Arc::new(vec![123u8])
+    }
+
+    fn given_a_pointer() -> DataPointer {
+        DataPointer::new_scalar(DataType::Byte, 0, Endianness::BigEndian)
     }
 
     #[test]
     fn value_returns_the_value() {
-        let name = "test1".to_string();
-        let data_pointer = given_a_pointer(&name);
+        let region = given_a_region();
         let variability = Variability::Monotonic;
         let unit = Unit::Hertz;
-        let tested_entry = VariableEntry::new(data_pointer, variability, unit);
+        let tested_entry = VariableEntry::new(region, given_a_pointer(), variability, unit);
 
         match tested_entry.value().unwrap() {
             EntryValue::Byte(x) => assert_eq!(x, 123),
-            _ => assert!(false),
+            _ => unreachable!(),
         }
     }
 
     #[test]
     fn variability_returns_the_variability() {
-        let name = "test1".to_string();
-        let data_pointer = given_a_pointer(&name);
+        let region = given_a_region();
         let variability = Variability::Monotonic;
         let unit = Unit::Hertz;
-        let tested_entry = VariableEntry::new(data_pointer, variability, unit);
+        let tested_entry = VariableEntry::new(region, given_a_pointer(), variability, unit);
 
         assert_eq!(variability, tested_entry.variability());
     }
 
     #[test]
     fn unit_returns_the_unit() {
-        let name = "test1".to_string();
-        let data_pointer = given_a_pointer(&name);
+        let region = given_a_region();
         let variability = Variability::Monotonic;
         let unit = Unit::Hertz;
-        let tested_entry = VariableEntry::new(data_pointer, variability, unit);
+        let tested_entry = VariableEntry::new(region, given_a_pointer(), variability, unit);
 
         assert_eq!(unit, tested_entry.unit());
     }
+
+    #[test]
+    fn clones_read_the_same_value_from_other_threads() {
+        let region = given_a_region();
+        let tested_entry = VariableEntry::new(
+            region,
+            given_a_pointer(),
+            Variability::Variable,
+            Unit::Bytes,
+        );
+
+        let clone = tested_entry.clone();
+        drop(tested_entry);
+        let value = std::thread::spawn(move || clone.value().unwrap())
+            .join()
+            .unwrap();
+
+        assert!(matches!(value, EntryValue::Byte(123)));
+    }
 }
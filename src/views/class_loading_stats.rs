@@ -0,0 +1,253 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::{
+    errors::Error,
+    jvm_clock,
+    jvm_monitor::Entry,
+    views::{Lookup, optional},
+};
+
+/// Classes loaded and unloaded by a JVM and the time spent on them, as shown by
+/// `jstat -class`, read from java.cls.*, sun.cls.* and the timers of the class loaders.
+#[derive(Debug, Clone)]
+pub struct ClassLoadingStats {
+    loaded_classes: u64,
+    shared_loaded_classes: u64,
+    unloaded_classes: u64,
+    shared_unloaded_classes: Option<u64>,
+    loaded_bytes: Option<u64>,
+    shared_loaded_bytes: Option<u64>,
+    unloaded_bytes: Option<u64>,
+    shared_unloaded_bytes: Option<u64>,
+    time: Duration,
+    parse_class_time: Option<Duration>,
+    class_verify_time: Option<Duration>,
+    class_init_time: Option<Duration>,
+    define_app_class_time: Option<Duration>,
+    read_class_bytes_time: Option<Duration>,
+    find_class_time: Option<Duration>,
+    parent_delegation_time: Option<Duration>,
+    uptime: Option<Duration>,
+}
+
+impl ClassLoadingStats {
+    pub fn from_entries(entries: &HashMap<String, Entry>) -> Result<Self, Error> {
+        let lookup = Lookup::new(entries);
+        Ok(Self {
+            loaded_classes: lookup.count("java.cls.loadedClasses")?,
+            shared_loaded_classes: lookup.count("java.cls.sharedLoadedClasses")?,
+            unloaded_classes: lookup.count("java.cls.unloadedClasses")?,
+            shared_unloaded_classes: optional(lookup.count("java.cls.sharedUnloadedClasses"))?,
+            loaded_bytes: optional(lookup.bytes("sun.cls.loadedBytes"))?,
+            shared_loaded_bytes: optional(lookup.bytes("sun.cls.sharedLoadedBytes"))?,
+            unloaded_bytes: optional(lookup.bytes("sun.cls.unloadedBytes"))?,
+            shared_unloaded_bytes: optional(lookup.bytes("sun.cls.sharedUnloadedBytes"))?,
+            time: lookup.duration("sun.cls.time")?,
+            parse_class_time: optional(lookup.duration("sun.cls.parseClassTime"))?,
+            class_verify_time: optional(lookup.duration("sun.cls.classVerifyTime"))?,
+            class_init_time: optional(lookup.duration("sun.cls.classInitTime"))?,
+            define_app_class_time: optional(lookup.duration("sun.cls.defineAppClassTime"))?,
+            read_class_bytes_time: optional(
+                lookup.nanoseconds("sun.urlClassLoader.readClassBytesTime"),
+            )?,
+            find_class_time: optional(lookup.nanoseconds("sun.classloader.findClassTime"))?,
+            parent_delegation_time: optional(
+                lookup.nanoseconds("sun.classloader.parentDelegationTime"),
+            )?,
+            uptime: optional(lookup.duration(jvm_clock::TICKS))?,
+        })
+    }
+
+    /// Number of classes loaded, including the ones from the shared archive, as the
+    /// Loaded column of `jstat -class`.
+    pub fn loaded(&self) -> u64 {
+        self.loaded_classes + self.shared_loaded_classes
+    }
+
+    /// Number of classes unloaded, including the ones from the shared archive.
+    pub fn unloaded(&self) -> u64 {
+        self.unloaded_classes + self.shared_unloaded_classes.unwrap_or(0)
+    }
+
+    /// Bytes of the classes loaded, when published.
+    pub fn loaded_bytes(&self) -> Option<u64> {
+        Some(self.loaded_bytes? + self.shared_loaded_bytes.unwrap_or(0))
+    }
+
+    /// Bytes of the classes unloaded, when published.
+    pub fn unloaded_bytes(&self) -> Option<u64> {
+        Some(self.unloaded_bytes? + self.shared_unloaded_bytes.unwrap_or(0))
+    }
+
+    /// Number of classes loaded from class files.
+    pub fn loaded_classes(&self) -> u64 {
+        self.loaded_classes
+    }
+
+    /// Number of classes loaded from the shared archive.
+    pub fn shared_loaded_classes(&self) -> u64 {
+        self.shared_loaded_classes
+    }
+
+    pub fn unloaded_classes(&self) -> u64 {
+        self.unloaded_classes
+    }
+
+    pub fn shared_unloaded_classes(&self) -> Option<u64> {
+        self.shared_unloaded_classes
+    }
+
+    /// Time spent loading and unloading classes, as the Time column of `jstat -class`.
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    /// Time spent parsing class files.
+    pub fn parse_class_time(&self) -> Option<Duration> {
+        self.parse_class_time
+    }
+
+    /// Time spent verifying the bytecode.
+    pub fn class_verify_time(&self) -> Option<Duration> {
+        self.class_verify_time
+    }
+
+    /// Time spent running the static initializers.
+    pub fn class_init_time(&self) -> Option<Duration> {
+        self.class_init_time
+    }
+
+    /// Time spent defining the classes of the application class loaders.
+    pub fn define_app_class_time(&self) -> Option<Duration> {
+        self.define_app_class_time
+    }
+
+    /// Time the URL class loaders spent reading class files.
+    pub fn read_class_bytes_time(&self) -> Option<Duration> {
+        self.read_class_bytes_time
+    }
+
+    /// Time the class loaders spent finding classes themselves.
+    pub fn find_class_time(&self) -> Option<Duration> {
+        self.find_class_time
+    }
+
+    /// Time the class loaders spent delegating to their parents.
+    pub fn parent_delegation_time(&self) -> Option<Duration> {
+        self.parent_delegation_time
+    }
+
+    /// Fraction of the time since the start of the JVM spent on classes, between 0 and 1,
+    /// when sun.os.hrt.ticks is published.
+    pub fn time_fraction(&self) -> Option<f64> {
+        let uptime = self.uptime.filter(|uptime| !uptime.is_zero())?;
+        Some((self.time.as_secs_f64() / uptime.as_secs_f64()).min(1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{ClassLoadingStats, EntryValue, Error, Unit, Variability, testing::FakeJvm};
+
+    fn given_a_jvm(counters: &[(&str, i64, Unit)]) -> FakeJvm {
+        counters
+            .iter()
+            .fold(FakeJvm::new(), |jvm, (name, value, unit)| {
+                jvm.entry(name, EntryValue::Long(*value), *unit, Variability::Variable)
+            })
+    }
+
+    fn given_a_minimal_jvm() -> FakeJvm {
+        given_a_jvm(&[
+            ("java.cls.loadedClasses", 2000, Unit::Events),
+            ("java.cls.sharedLoadedClasses", 600, Unit::Events),
+            ("java.cls.unloadedClasses", 30, Unit::Events),
+            ("sun.cls.time", 3_000, Unit::Ticks),
+            ("sun.os.hrt.frequency", 1000, Unit::Hertz),
+        ])
+    }
+
+    fn given_a_complete_jvm() -> FakeJvm {
+        [
+            ("java.cls.sharedUnloadedClasses", 2, Unit::Events),
+            ("sun.cls.loadedBytes", 4_000_000, Unit::Bytes),
+            ("sun.cls.sharedLoadedBytes", 1_000_000, Unit::Bytes),
+            ("sun.cls.unloadedBytes", 60_000, Unit::Bytes),
+            ("sun.cls.sharedUnloadedBytes", 0, Unit::Bytes),
+            ("sun.cls.parseClassTime", 1_200, Unit::Ticks),
+            ("sun.cls.classVerifyTime", 800, Unit::Ticks),
+            ("sun.cls.classInitTime", 500, Unit::Ticks),
+            ("sun.cls.defineAppClassTime", 300, Unit::Ticks),
+            (
+                "sun.urlClassLoader.readClassBytesTime",
+                250_000_000,
+                Unit::None,
+            ),
+            ("sun.classloader.findClassTime", 400_000_000, Unit::None),
+            (
+                "sun.classloader.parentDelegationTime",
+                100_000_000,
+                Unit::None,
+            ),
+            ("sun.os.hrt.ticks", 60_000, Unit::Ticks),
+        ]
+        .into_iter()
+        .fold(given_a_minimal_jvm(), |jvm, (name, value, unit)| {
+            jvm.entry(name, EntryValue::Long(value), unit, Variability::Variable)
+        })
+    }
+
+    fn read(jvm: &FakeJvm) -> Result<ClassLoadingStats, Error> {
+        ClassLoadingStats::from_entries(jvm.snapshot().unwrap().entries())
+    }
+
+    #[test]
+    fn totals_mirror_jstat() {
+        let stats = read(&given_a_complete_jvm()).unwrap();
+
+        assert_eq!(2600, stats.loaded());
+        assert_eq!(32, stats.unloaded());
+        assert_eq!(Some(5_000_000), stats.loaded_bytes());
+        assert_eq!(Some(60_000), stats.unloaded_bytes());
+        assert_eq!(Duration::from_secs(3), stats.time());
+    }
+
+    #[test]
+    fn phases_are_durations() {
+        let stats = read(&given_a_complete_jvm()).unwrap();
+
+        assert_eq!(Some(Duration::from_millis(1200)), stats.parse_class_time());
+        assert_eq!(Some(Duration::from_millis(800)), stats.class_verify_time());
+        assert_eq!(Some(Duration::from_millis(500)), stats.class_init_time());
+        assert_eq!(
+            Some(Duration::from_millis(300)),
+            stats.define_app_class_time()
+        );
+        assert_eq!(
+            Some(Duration::from_millis(250)),
+            stats.read_class_bytes_time()
+        );
+        assert_eq!(Some(Duration::from_millis(400)), stats.find_class_time());
+        assert_eq!(
+            Some(Duration::from_millis(100)),
+            stats.parent_delegation_time()
+        );
+        assert_eq!(Some(0.05), stats.time_fraction());
+    }
+
+    #[test]
+    fn optional_counters_may_be_missing() {
+        let stats = read(&given_a_minimal_jvm()).unwrap();
+
+        assert_eq!(30, stats.unloaded());
+        assert_eq!(None, stats.loaded_bytes());
+        assert_eq!(None, stats.class_verify_time());
+        assert_eq!(None, stats.time_fraction());
+        assert!(matches!(
+            read(&FakeJvm::new()),
+            Err(Error::MissingEntry { .. })
+        ));
+    }
+}
@@ -0,0 +1,335 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::{
+    errors::Error,
+    jvm_monitor::Entry,
+    views::{Lookup, optional},
+};
+
+/// Kind of a compilation, as published in the sun.ci.*Type entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompileType {
+    /// No compilation happened yet.
+    None,
+    /// Compilation of a whole method.
+    Normal,
+    /// On-stack replacement of a running loop.
+    Osr,
+    /// Wrapper of a native method.
+    Native,
+    /// A type this crate does not know.
+    Unknown(i64),
+}
+
+impl From<i64> for CompileType {
+    fn from(value: i64) -> Self {
+        match value {
+            0 => CompileType::None,
+            1 => CompileType::Normal,
+            2 => CompileType::Osr,
+            3 => CompileType::Native,
+            value => CompileType::Unknown(value),
+        }
+    }
+}
+
+/// Activity of the JIT compilers of a JVM, as shown by `jstat -compiler`, read from
+/// sun.ci.* and java.ci.totalTime.
+#[derive(Debug, Clone)]
+pub struct CompilerStats {
+    total_compiles: u64,
+    osr_compiles: u64,
+    standard_compiles: u64,
+    invalidations: u64,
+    bailouts: u64,
+    standard_bytes: u64,
+    osr_bytes: u64,
+    total_time: Duration,
+    standard_time: Option<Duration>,
+    osr_time: Option<Duration>,
+    last_method: String,
+    last_type: CompileType,
+    last_failed_method: String,
+    last_failed_type: CompileType,
+    last_invalidated_method: Option<String>,
+    threads: Vec<CompilerThreadStats>,
+}
+
+impl CompilerStats {
+    pub fn from_entries(entries: &HashMap<String, Entry>) -> Result<Self, Error> {
+        let lookup = Lookup::new(entries);
+        let threads = lookup
+            .indices("sun.ci.compilerThread", "compiles")
+            .into_iter()
+            .map(|index| CompilerThreadStats::read(&lookup, index))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            total_compiles: lookup.count("sun.ci.totalCompiles")?,
+            osr_compiles: lookup.count("sun.ci.osrCompiles")?,
+            standard_compiles: lookup.count("sun.ci.standardCompiles")?,
+            invalidations: lookup.count("sun.ci.totalInvalidates")?,
+            bailouts: lookup.count("sun.ci.totalBailouts")?,
+            standard_bytes: lookup.bytes("sun.ci.standardBytes")?,
+            osr_bytes: lookup.bytes("sun.ci.osrBytes")?,
+            total_time: lookup.duration("java.ci.totalTime")?,
+            standard_time: optional(lookup.duration("sun.ci.standardTime"))?,
+            osr_time: optional(lookup.duration("sun.ci.osrTime"))?,
+            last_method: lookup.string("sun.ci.lastMethod")?,
+            last_type: lookup.long("sun.ci.lastType")?.into(),
+            last_failed_method: lookup.string("sun.ci.lastFailedMethod")?,
+            last_failed_type: lookup.long("sun.ci.lastFailedType")?.into(),
+            last_invalidated_method: optional(lookup.string("sun.ci.lastInvalidatedMethod"))?,
+            threads,
+        })
+    }
+
+    /// Number of compilations, the Compiled column of `jstat -compiler`.
+    pub fn total_compiles(&self) -> u64 {
+        self.total_compiles
+    }
+
+    /// Number of on-stack replacements.
+    pub fn osr_compiles(&self) -> u64 {
+        self.osr_compiles
+    }
+
+    pub fn standard_compiles(&self) -> u64 {
+        self.standard_compiles
+    }
+
+    /// Number of compiled methods thrown away, the Invalid column of `jstat -compiler`.
+    pub fn invalidations(&self) -> u64 {
+        self.invalidations
+    }
+
+    /// Number of compilations that failed, the Failed column of `jstat -compiler`.
+    pub fn bailouts(&self) -> u64 {
+        self.bailouts
+    }
+
+    /// Bytes of bytecode compiled.
+    pub fn compiled_bytes(&self) -> u64 {
+        self.standard_bytes + self.osr_bytes
+    }
+
+    pub fn standard_bytes(&self) -> u64 {
+        self.standard_bytes
+    }
+
+    pub fn osr_bytes(&self) -> u64 {
+        self.osr_bytes
+    }
+
+    /// Time spent compiling, the Time column of `jstat -compiler`.
+    pub fn total_time(&self) -> Duration {
+        self.total_time
+    }
+
+    pub fn standard_time(&self) -> Option<Duration> {
+        self.standard_time
+    }
+
+    pub fn osr_time(&self) -> Option<Duration> {
+        self.osr_time
+    }
+
+    /// Average time of a compilation, None without compilations.
+    pub fn average_time(&self) -> Option<Duration> {
+        let nanos = self
+            .total_time
+            .as_nanos()
+            .checked_div(self.total_compiles.into())?;
+        Some(Duration::from_nanos(nanos as u64))
+    }
+
+    /// The method compiled last, empty before the first compilation.
+    pub fn last_method(&self) -> &str {
+        &self.last_method
+    }
+
+    pub fn last_type(&self) -> CompileType {
+        self.last_type
+    }
+
+    /// The method whose compilation failed last, the FailedMethod column of
+    /// `jstat -compiler`.
+    pub fn last_failed_method(&self) -> &str {
+        &self.last_failed_method
+    }
+
+    pub fn last_failed_type(&self) -> CompileType {
+        self.last_failed_type
+    }
+
+    pub fn last_invalidated_method(&self) -> Option<&str> {
+        self.last_invalidated_method.as_deref()
+    }
+
+    /// The compiler threads, ordered by index, when the JVM publishes them.
+    pub fn threads(&self) -> &[CompilerThreadStats] {
+        &self.threads
+    }
+}
+
+/// Activity of one compiler thread.
+#[derive(Debug, Clone)]
+pub struct CompilerThreadStats {
+    index: usize,
+    compiles: u64,
+    time: Duration,
+    method: String,
+    compile_type: CompileType,
+}
+
+impl CompilerThreadStats {
+    fn read(lookup: &Lookup, index: usize) -> Result<Self, Error> {
+        let name = |field: &str| format!("sun.ci.compilerThread.{index}.{field}");
+        Ok(Self {
+            index,
+            compiles: lookup.count(&name("compiles"))?,
+            time: lookup.duration(&name("time"))?,
+            method: lookup.string(&name("method"))?,
+            compile_type: lookup.long(&name("type"))?.into(),
+        })
+    }
+
+    /// The N of sun.ci.compilerThread.N.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn compiles(&self) -> u64 {
+        self.compiles
+    }
+
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    /// The method being compiled, or compiled last.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    pub fn compile_type(&self) -> CompileType {
+        self.compile_type
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        CompileType, CompilerStats, EntryValue, Error, Unit, Variability, testing::FakeJvm,
+    };
+
+    fn long(jvm: FakeJvm, name: &str, value: i64, unit: Unit) -> FakeJvm {
+        jvm.entry(name, EntryValue::Long(value), unit, Variability::Variable)
+    }
+
+    fn string(jvm: FakeJvm, name: &str, value: &str) -> FakeJvm {
+        jvm.entry(
+            name,
+            EntryValue::String(value.to_string()),
+            Unit::String,
+            Variability::Variable,
+        )
+    }
+
+    fn given_a_jvm() -> FakeJvm {
+        let jvm = [
+            ("sun.os.hrt.frequency", 1000, Unit::Hertz),
+            ("sun.ci.totalCompiles", 400, Unit::Events),
+            ("sun.ci.osrCompiles", 10, Unit::Events),
+            ("sun.ci.standardCompiles", 390, Unit::Events),
+            ("sun.ci.totalInvalidates", 3, Unit::Events),
+            ("sun.ci.totalBailouts", 1, Unit::Events),
+            ("sun.ci.standardBytes", 90_000, Unit::Bytes),
+            ("sun.ci.osrBytes", 10_000, Unit::Bytes),
+            ("java.ci.totalTime", 2_000, Unit::Ticks),
+            ("sun.ci.standardTime", 1_800, Unit::Ticks),
+            ("sun.ci.osrTime", 200, Unit::Ticks),
+            ("sun.ci.lastType", 2, Unit::None),
+            ("sun.ci.lastFailedType", 1, Unit::None),
+        ]
+        .into_iter()
+        .fold(FakeJvm::new(), |jvm, (name, value, unit)| {
+            long(jvm, name, value, unit)
+        });
+        let jvm = string(jvm, "sun.ci.lastMethod", "java/util/HashMap get");
+        string(jvm, "sun.ci.lastFailedMethod", "org/example/Huge method")
+    }
+
+    fn given_a_thread(jvm: FakeJvm, index: usize, compiles: i64, method: &str) -> FakeJvm {
+        let name = |field: &str| format!("sun.ci.compilerThread.{index}.{field}");
+        let jvm = long(jvm, &name("compiles"), compiles, Unit::Events);
+        let jvm = long(jvm, &name("time"), compiles * 5, Unit::Ticks);
+        let jvm = long(jvm, &name("type"), 1, Unit::None);
+        string(jvm, &name("method"), method)
+    }
+
+    fn read(jvm: &FakeJvm) -> Result<CompilerStats, Error> {
+        CompilerStats::from_entries(jvm.snapshot().unwrap().entries())
+    }
+
+    #[test]
+    fn counters_mirror_jstat() {
+        let stats = read(&given_a_jvm()).unwrap();
+
+        assert_eq!(400, stats.total_compiles());
+        assert_eq!(1, stats.bailouts());
+        assert_eq!(3, stats.invalidations());
+        assert_eq!(Duration::from_secs(2), stats.total_time());
+        assert_eq!("org/example/Huge method", stats.last_failed_method());
+        assert_eq!(CompileType::Normal, stats.last_failed_type());
+    }
+
+    #[test]
+    fn compilations_are_broken_down() {
+        let stats = read(&given_a_jvm()).unwrap();
+
+        assert_eq!(10, stats.osr_compiles());
+        assert_eq!(100_000, stats.compiled_bytes());
+        assert_eq!(Some(Duration::from_millis(200)), stats.osr_time());
+        assert_eq!(Some(Duration::from_millis(5)), stats.average_time());
+        assert_eq!("java/util/HashMap get", stats.last_method());
+        assert_eq!(CompileType::Osr, stats.last_type());
+        assert_eq!(None, stats.last_invalidated_method());
+        assert!(stats.threads().is_empty());
+    }
+
+    #[test]
+    fn average_time_counts_all_the_compilations() {
+        let mut jvm = given_a_jvm();
+        jvm.set("sun.ci.totalCompiles", EntryValue::Long(1 << 33))
+            .unwrap();
+        jvm.set("java.ci.totalTime", EntryValue::Long(1 << 33))
+            .unwrap();
+
+        let stats = read(&jvm).unwrap();
+
+        assert_eq!(Some(Duration::from_millis(1)), stats.average_time());
+    }
+
+    #[test]
+    fn compiler_threads_are_enumerated() {
+        let jvm = given_a_thread(given_a_jvm(), 1, 300, "java/lang/String hashCode");
+        let jvm = given_a_thread(jvm, 0, 100, "");
+
+        let stats = read(&jvm).unwrap();
+
+        let compiles: Vec<u64> = stats.threads().iter().map(|t| t.compiles()).collect();
+        assert_eq!(vec![100, 300], compiles);
+        assert_eq!(Duration::from_millis(1500), stats.threads()[1].time());
+        assert_eq!("java/lang/String hashCode", stats.threads()[1].method());
+        assert_eq!(CompileType::Normal, stats.threads()[1].compile_type());
+    }
+
+    #[test]
+    fn unknown_compile_types_are_kept() {
+        assert_eq!(CompileType::None, CompileType::from(0));
+        assert_eq!(CompileType::Native, CompileType::from(3));
+        assert_eq!(CompileType::Unknown(7), CompileType::from(7));
+    }
+}
@@ -0,0 +1,288 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::{
+    errors::Error,
+    jvm_monitor::Entry,
+    views::{Lookup, optional},
+};
+
+/// Statistics of the garbage collectors of a JVM, read from sun.gc.collector.N.* and the
+/// causes of the collections.
+#[derive(Debug, Clone)]
+pub struct GcStats {
+    collectors: Vec<CollectorStats>,
+    cause: Option<String>,
+    last_cause: Option<String>,
+}
+
+impl GcStats {
+    /// Reads the statistics of all the collectors published in the entries.
+    pub fn from_entries(entries: &HashMap<String, Entry>) -> Result<Self, Error> {
+        let lookup = Lookup::new(entries);
+        let collectors = lookup
+            .indices("sun.gc.collector", "name")
+            .into_iter()
+            .map(|index| CollectorStats::read(&lookup, index))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            collectors,
+            cause: optional(lookup.string("sun.gc.cause"))?,
+            last_cause: optional(lookup.string("sun.gc.lastCause"))?,
+        })
+    }
+
+    /// The collectors, ordered by index.
+    pub fn collectors(&self) -> &[CollectorStats] {
+        &self.collectors
+    }
+
+    /// The collector with the name, e.g. "G1 Young Generation" or "Shenandoah Pauses".
+    pub fn collector(&self, name: &str) -> Option<&CollectorStats> {
+        self.collectors
+            .iter()
+            .find(|collector| collector.name == name)
+    }
+
+    /// The cause of the collection in progress, "No GC" between collections.
+    pub fn cause(&self) -> Option<&str> {
+        self.cause.as_deref()
+    }
+
+    /// The cause of the last collection.
+    pub fn last_cause(&self) -> Option<&str> {
+        self.last_cause.as_deref()
+    }
+
+    /// Total time spent collecting by all the collectors.
+    pub fn total_time(&self) -> Duration {
+        self.collectors.iter().map(CollectorStats::time).sum()
+    }
+}
+
+/// Statistics of one garbage collector.
+#[derive(Debug, Clone)]
+pub struct CollectorStats {
+    index: usize,
+    name: String,
+    invocations: u64,
+    time: Duration,
+    last_entry_time: Duration,
+    last_exit_time: Duration,
+}
+
+impl CollectorStats {
+    fn read(lookup: &Lookup, index: usize) -> Result<Self, Error> {
+        let name = |field: &str| format!("sun.gc.collector.{index}.{field}");
+        Ok(Self {
+            index,
+            name: lookup.string(&name("name"))?,
+            invocations: lookup.count(&name("invocations"))?,
+            time: lookup.duration(&name("time"))?,
+            last_entry_time: lookup.duration(&name("lastEntryTime"))?,
+            last_exit_time: lookup.duration(&name("lastExitTime"))?,
+        })
+    }
+
+    /// The N of sun.gc.collector.N.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Number of collections started.
+    pub fn invocations(&self) -> u64 {
+        self.invocations
+    }
+
+    /// Time spent in the collections.
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    /// Start of the last collection, since the start of the JVM.
+    pub fn last_entry_time(&self) -> Duration {
+        self.last_entry_time
+    }
+
+    /// End of the last collection, since the start of the JVM.
+    pub fn last_exit_time(&self) -> Duration {
+        self.last_exit_time
+    }
+
+    /// Whether a collection has started and not ended yet.
+    pub fn in_progress(&self) -> bool {
+        self.last_entry_time > self.last_exit_time
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        EntryValue, Error, GcStats, Unit, Variability, snapshot::Snapshot, testing::FakeJvm,
+    };
+
+    fn given_a_collector(jvm: FakeJvm, index: usize, name: &str, times: [i64; 3]) -> FakeJvm {
+        let [time, entry, exit] = times;
+        let field = |field: &str| format!("sun.gc.collector.{index}.{field}");
+        jvm.entry(
+            &field("name"),
+            EntryValue::String(name.to_string()),
+            Unit::String,
+            Variability::Constant,
+        )
+        .entry(
+            &field("invocations"),
+            EntryValue::Long(index as i64 + 1),
+            Unit::Events,
+            Variability::Monotonic,
+        )
+        .entry(
+            &field("time"),
+            EntryValue::Long(time),
+            Unit::Ticks,
+            Variability::Monotonic,
+        )
+        .entry(
+            &field("lastEntryTime"),
+            EntryValue::Long(entry),
+            Unit::Ticks,
+            Variability::Variable,
+        )
+        .entry(
+            &field("lastExitTime"),
+            EntryValue::Long(exit),
+            Unit::Ticks,
+            Variability::Variable,
+        )
+    }
+
+    fn given_a_jvm() -> FakeJvm {
+        let jvm = FakeJvm::new()
+            .entry(
+                "sun.os.hrt.frequency",
+                EntryValue::Long(1000),
+                Unit::Hertz,
+                Variability::Constant,
+            )
+            .entry(
+                "sun.gc.cause",
+                EntryValue::String("No GC".to_string()),
+                Unit::String,
+                Variability::Variable,
+            )
+            .entry(
+                "sun.gc.lastCause",
+                EntryValue::String("G1 Evacuation Pause".to_string()),
+                Unit::String,
+                Variability::Variable,
+            );
+        let jvm = given_a_collector(jvm, 0, "G1 Young Generation", [250, 4000, 4100]);
+        let jvm = given_a_collector(jvm, 1, "G1 Old Generation", [0, 0, 0]);
+        given_a_collector(jvm, 12, "G1 Concurrent GC", [1500, 5000, 3000])
+    }
+
+    fn read(snapshot: &Snapshot) -> Result<GcStats, Error> {
+        GcStats::from_entries(snapshot.entries())
+    }
+
+    #[test]
+    fn collectors_are_enumerated_by_index() {
+        let stats = read(&given_a_jvm().snapshot().unwrap()).unwrap();
+
+        let names: Vec<&str> = stats.collectors().iter().map(|c| c.name()).collect();
+        assert_eq!(
+            vec![
+                "G1 Young Generation",
+                "G1 Old Generation",
+                "G1 Concurrent GC"
+            ],
+            names
+        );
+        assert_eq!(12, stats.collectors()[2].index());
+        assert_eq!(Some("No GC"), stats.cause());
+        assert_eq!(Some("G1 Evacuation Pause"), stats.last_cause());
+    }
+
+    #[test]
+    fn times_are_converted_with_the_frequency() {
+        let stats = read(&given_a_jvm().snapshot().unwrap()).unwrap();
+        let young = stats.collector("G1 Young Generation").unwrap();
+
+        assert_eq!(1, young.invocations());
+        assert_eq!(Duration::from_millis(250), young.time());
+        assert_eq!(Duration::from_secs(4), young.last_entry_time());
+        assert_eq!(Duration::from_millis(4100), young.last_exit_time());
+        assert_eq!(Duration::from_millis(1750), stats.total_time());
+    }
+
+    #[test]
+    fn collections_in_progress_started_after_the_last_end() {
+        let stats = read(&given_a_jvm().snapshot().unwrap()).unwrap();
+
+        let in_progress: Vec<bool> = stats.collectors().iter().map(|c| c.in_progress()).collect();
+        assert_eq!(vec![false, false, true], in_progress);
+    }
+
+    #[test]
+    fn causes_are_optional() {
+        let jvm = given_a_collector(
+            FakeJvm::new().entry(
+                "sun.os.hrt.frequency",
+                EntryValue::Long(1000),
+                Unit::Hertz,
+                Variability::Constant,
+            ),
+            0,
+            "Serial",
+            [0, 0, 0],
+        );
+
+        let stats = read(&jvm.snapshot().unwrap()).unwrap();
+
+        assert_eq!(None, stats.cause());
+        assert_eq!(1, stats.collectors().len());
+    }
+
+    #[test]
+    fn incomplete_collectors_are_refused() {
+        let jvm = given_a_jvm().entry(
+            "sun.gc.collector.3.name",
+            EntryValue::String("Unknown".to_string()),
+            Unit::String,
+            Variability::Constant,
+        );
+
+        assert!(matches!(
+            read(&jvm.snapshot().unwrap()),
+            Err(Error::MissingEntry { .. })
+        ));
+    }
+
+    #[test]
+    fn negative_invocations_are_refused() {
+        let mut jvm = given_a_jvm();
+        jvm.set("sun.gc.collector.1.invocations", EntryValue::Long(-1))
+            .unwrap();
+
+        assert!(matches!(
+            read(&jvm.snapshot().unwrap()),
+            Err(Error::UnexpectedValue { name }) if name == "sun.gc.collector.1.invocations"
+        ));
+    }
+
+    #[test]
+    fn collectors_are_read_from_monitors() {
+        let mut jvm = given_a_jvm();
+        let monitor = jvm.monitor().unwrap();
+
+        jvm.add("sun.gc.collector.0.invocations", 4).unwrap();
+
+        let stats = GcStats::from_entries(monitor.entries()).unwrap();
+        assert_eq!(5, stats.collectors()[0].invocations());
+    }
+}
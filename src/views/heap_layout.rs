@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+
+use crate::{
+    errors::Error,
+    jvm_monitor::Entry,
+    views::{Lookup, optional, percentage},
+};
+
+/// Layout of the heap of a JVM, read from its sun.gc.generation.N.space.M.* entries.
+///
+/// Generations and spaces are enumerated from the ones published, as collectors publish
+/// different ones, e.g. eden and two survivors then old with G1, or none with ZGC.
+#[derive(Debug, Clone)]
+pub struct HeapLayout {
+    generations: Vec<Generation>,
+}
+
+impl HeapLayout {
+    /// Reads all the generations published in the entries.
+    pub fn from_entries(entries: &HashMap<String, Entry>) -> Result<Self, Error> {
+        let lookup = Lookup::new(entries);
+        let generations = lookup
+            .indices("sun.gc.generation", "name")
+            .into_iter()
+            .map(|index| Generation::read(&lookup, index))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { generations })
+    }
+
+    /// The generations, ordered by index.
+    pub fn generations(&self) -> &[Generation] {
+        &self.generations
+    }
+
+    /// The generation with the name, e.g. "young" or "old".
+    pub fn generation(&self, name: &str) -> Option<&Generation> {
+        self.generations
+            .iter()
+            .find(|generation| generation.name == name)
+    }
+
+    /// Bytes committed to all the generations.
+    pub fn capacity(&self) -> u64 {
+        self.generations.iter().map(Generation::capacity).sum()
+    }
+
+    /// Bytes used in all the generations.
+    pub fn used(&self) -> u64 {
+        self.generations.iter().map(Generation::used).sum()
+    }
+
+    /// Percentage of the committed bytes used.
+    pub fn occupancy(&self) -> f64 {
+        percentage(self.used(), self.capacity())
+    }
+}
+
+/// Generation of the heap, made of spaces.
+#[derive(Debug, Clone)]
+pub struct Generation {
+    index: usize,
+    name: String,
+    capacity: u64,
+    min_capacity: Option<u64>,
+    max_capacity: Option<u64>,
+    spaces: Vec<Space>,
+}
+
+impl Generation {
+    fn read(lookup: &Lookup, index: usize) -> Result<Self, Error> {
+        let prefix = format!("sun.gc.generation.{index}");
+        let name = |field: &str| format!("{prefix}.{field}");
+        let spaces = lookup
+            .indices(&name("space"), "name")
+            .into_iter()
+            .map(|space| Space::read(lookup, &prefix, space))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            index,
+            name: lookup.string(&name("name"))?,
+            capacity: lookup.bytes(&name("capacity"))?,
+            min_capacity: optional(lookup.bytes(&name("minCapacity")))?,
+            max_capacity: optional(lookup.bytes(&name("maxCapacity")))?,
+            spaces,
+        })
+    }
+
+    /// The N of sun.gc.generation.N.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Bytes committed to the generation.
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    pub fn min_capacity(&self) -> Option<u64> {
+        self.min_capacity
+    }
+
+    pub fn max_capacity(&self) -> Option<u64> {
+        self.max_capacity
+    }
+
+    /// The spaces, ordered by index.
+    pub fn spaces(&self) -> &[Space] {
+        &self.spaces
+    }
+
+    /// The space with the name, e.g. "eden", "s0" or "old".
+    pub fn space(&self, name: &str) -> Option<&Space> {
+        self.spaces.iter().find(|space| space.name == name)
+    }
+
+    /// Bytes used in the spaces of the generation.
+    pub fn used(&self) -> u64 {
+        self.spaces.iter().map(Space::used).sum()
+    }
+
+    /// Percentage of the committed bytes used.
+    pub fn occupancy(&self) -> f64 {
+        percentage(self.used(), self.capacity)
+    }
+}
+
+/// Space of a generation.
+#[derive(Debug, Clone)]
+pub struct Space {
+    index: usize,
+    name: String,
+    capacity: u64,
+    used: u64,
+    init_capacity: Option<u64>,
+    max_capacity: Option<u64>,
+}
+
+impl Space {
+    fn read(lookup: &Lookup, generation: &str, index: usize) -> Result<Self, Error> {
+        let name = |field: &str| format!("{generation}.space.{index}.{field}");
+        Ok(Self {
+            index,
+            name: lookup.string(&name("name"))?,
+            capacity: lookup.bytes(&name("capacity"))?,
+            used: lookup.bytes(&name("used"))?,
+            init_capacity: optional(lookup.bytes(&name("initCapacity")))?,
+            max_capacity: optional(lookup.bytes(&name("maxCapacity")))?,
+        })
+    }
+
+    /// The M of sun.gc.generation.N.space.M.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Bytes committed to the space.
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    pub fn used(&self) -> u64 {
+        self.used
+    }
+
+    pub fn init_capacity(&self) -> Option<u64> {
+        self.init_capacity
+    }
+
+    pub fn max_capacity(&self) -> Option<u64> {
+        self.max_capacity
+    }
+
+    /// Percentage of the committed bytes used.
+    pub fn occupancy(&self) -> f64 {
+        percentage(self.used, self.capacity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EntryValue, Error, HeapLayout, Unit, Variability, testing::FakeJvm};
+
+    const MIB: i64 = 1 << 20;
+
+    fn given_a_generation(jvm: FakeJvm, index: usize, name: &str, capacity: i64) -> FakeJvm {
+        let field = |field: &str| format!("sun.gc.generation.{index}.{field}");
+        jvm.entry(
+            &field("name"),
+            EntryValue::String(name.to_string()),
+            Unit::String,
+            Variability::Constant,
+        )
+        .entry(
+            &field("capacity"),
+            EntryValue::Long(capacity),
+            Unit::Bytes,
+            Variability::Variable,
+        )
+        .entry(
+            &field("maxCapacity"),
+            EntryValue::Long(4 * capacity),
+            Unit::Bytes,
+            Variability::Constant,
+        )
+    }
+
+    fn given_a_space(
+        jvm: FakeJvm,
+        generation: usize,
+        index: usize,
+        name: &str,
+        [capacity, used]: [i64; 2],
+    ) -> FakeJvm {
+        let field = |field: &str| format!("sun.gc.generation.{generation}.space.{index}.{field}");
+        jvm.entry(
+            &field("name"),
+            EntryValue::String(name.to_string()),
+            Unit::String,
+            Variability::Constant,
+        )
+        .entry(
+            &field("capacity"),
+            EntryValue::Long(capacity),
+            Unit::Bytes,
+            Variability::Variable,
+        )
+        .entry(
+            &field("used"),
+            EntryValue::Long(used),
+            Unit::Bytes,
+            Variability::Variable,
+        )
+        .entry(
+            &field("initCapacity"),
+            EntryValue::Long(capacity / 2),
+            Unit::Bytes,
+            Variability::Constant,
+        )
+    }
+
+    fn given_a_jvm() -> FakeJvm {
+        let jvm = given_a_generation(FakeJvm::new(), 0, "young", 40 * MIB);
+        let jvm = given_a_space(jvm, 0, 0, "eden", [32 * MIB, 8 * MIB]);
+        let jvm = given_a_space(jvm, 0, 1, "s0", [4 * MIB, 0]);
+        let jvm = given_a_space(jvm, 0, 2, "s1", [4 * MIB, 4 * MIB]);
+        let jvm = given_a_generation(jvm, 1, "old", 60 * MIB);
+        given_a_space(jvm, 1, 0, "old", [60 * MIB, 15 * MIB])
+    }
+
+    fn read(jvm: &FakeJvm) -> Result<HeapLayout, Error> {
+        HeapLayout::from_entries(jvm.snapshot().unwrap().entries())
+    }
+
+    #[test]
+    fn generations_hold_their_spaces() {
+        let layout = read(&given_a_jvm()).unwrap();
+
+        let young = layout.generation("young").unwrap();
+        let names: Vec<&str> = young.spaces().iter().map(|space| space.name()).collect();
+        assert_eq!(vec!["eden", "s0", "s1"], names);
+        assert_eq!(Some(160 * MIB as u64), young.max_capacity());
+        assert_eq!(None, young.min_capacity());
+        assert_eq!(
+            Some(16 * MIB as u64),
+            young.space("eden").unwrap().init_capacity()
+        );
+        assert_eq!(1, layout.generation("old").unwrap().index());
+    }
+
+    #[test]
+    fn occupancy_is_the_percentage_used() {
+        let layout = read(&given_a_jvm()).unwrap();
+
+        let young = layout.generation("young").unwrap();
+        assert_eq!(12 * MIB as u64, young.used());
+        assert_eq!(25.0, young.space("eden").unwrap().occupancy());
+        assert_eq!(0.0, young.space("s0").unwrap().occupancy());
+        assert_eq!(30.0, young.occupancy());
+        assert_eq!(27.0, layout.occupancy());
+    }
+
+    #[test]
+    fn collectors_may_publish_fewer_spaces() {
+        let jvm = given_a_generation(FakeJvm::new(), 0, "young", 0);
+        let jvm = given_a_generation(jvm, 1, "old", 8 * MIB);
+        let jvm = given_a_space(jvm, 1, 0, "old", [8 * MIB, 2 * MIB]);
+
+        let layout = read(&jvm).unwrap();
+
+        assert!(layout.generations()[0].spaces().is_empty());
+        assert_eq!(0.0, layout.generations()[0].occupancy());
+        assert_eq!(25.0, layout.occupancy());
+        assert!(read(&FakeJvm::new()).unwrap().generations().is_empty());
+    }
+
+    #[test]
+    fn negative_sizes_are_refused() {
+        let jvm = given_a_space(given_a_jvm(), 1, 1, "broken", [-1, 0]);
+
+        assert!(matches!(
+            read(&jvm),
+            Err(Error::UnexpectedValue { name }) if name == "sun.gc.generation.1.space.1.capacity"
+        ));
+    }
+}
@@ -0,0 +1,99 @@
+use std::{collections::BTreeSet, collections::HashMap, time::Duration};
+
+use crate::{EntryValue, errors::Error, jvm_monitor::Entry};
+
+/// Typed access to the entries a view is made of.
+pub(crate) struct Lookup<'a>(&'a HashMap<String, Entry>);
+
+impl<'a> Lookup<'a> {
+    pub(crate) fn new(entries: &'a HashMap<String, Entry>) -> Self {
+        Self(entries)
+    }
+
+    pub(crate) fn entry(&self, name: &str) -> Result<&'a Entry, Error> {
+        self.0.get(name).ok_or_else(|| Error::MissingEntry {
+            name: name.to_string(),
+        })
+    }
+
+    /// The value of an integer entry, widened to i64.
+    pub(crate) fn long(&self, name: &str) -> Result<i64, Error> {
+        match self.entry(name)?.value()? {
+            EntryValue::Long(value) => Ok(value),
+            EntryValue::Int(value) => Ok(value.into()),
+            _ => Err(Self::unexpected(name)),
+        }
+    }
+
+    /// The value of an integer entry counting bytes, which cannot be negative.
+    pub(crate) fn bytes(&self, name: &str) -> Result<u64, Error> {
+        self.non_negative(name)
+    }
+
+    /// The value of an integer entry counting events, which cannot be negative.
+    pub(crate) fn count(&self, name: &str) -> Result<u64, Error> {
+        self.non_negative(name)
+    }
+
+    pub(crate) fn string(&self, name: &str) -> Result<String, Error> {
+        match self.entry(name)?.value()? {
+            EntryValue::String(value) => Ok(value),
+            _ => Err(Self::unexpected(name)),
+        }
+    }
+
+    /// The value of an entry in ticks, as a duration.
+    pub(crate) fn duration(&self, name: &str) -> Result<Duration, Error> {
+        self.entry(name)?.value_as_duration()
+    }
+
+    /// The value of an entry counting nanoseconds, as the timers of the class libraries do.
+    pub(crate) fn nanoseconds(&self, name: &str) -> Result<Duration, Error> {
+        self.count(name).map(Duration::from_nanos)
+    }
+
+    /// The indices N of the entries named prefix.N.suffix, in increasing order.
+    pub(crate) fn indices(&self, prefix: &str, suffix: &str) -> Vec<usize> {
+        self.0
+            .keys()
+            .filter_map(|name| {
+                name.strip_prefix(prefix)?
+                    .strip_prefix('.')?
+                    .strip_suffix(suffix)?
+                    .strip_suffix('.')?
+                    .parse()
+                    .ok()
+            })
+            .collect::<BTreeSet<usize>>()
+            .into_iter()
+            .collect()
+    }
+
+    fn non_negative(&self, name: &str) -> Result<u64, Error> {
+        u64::try_from(self.long(name)?).map_err(|_| Self::unexpected(name))
+    }
+
+    fn unexpected(name: &str) -> Error {
+        Error::UnexpectedValue {
+            name: name.to_string(),
+        }
+    }
+}
+
+/// The value of an entry that not all the JVMs publish, if published.
+pub(crate) fn optional<T>(result: Result<T, Error>) -> Result<Option<T>, Error> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(Error::MissingEntry { .. }) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Percentage of the whole made by the part, 0 when the whole is empty.
+pub(crate) fn percentage(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / whole as f64
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+
+use crate::{
+    errors::Error,
+    jvm_monitor::Entry,
+    views::{Lookup, optional, percentage},
+};
+
+/// Metaspace of a JVM, and its compressed class space, read from sun.gc.metaspace.* and
+/// sun.gc.compressedclassspace.*.
+#[derive(Debug, Clone)]
+pub struct Metaspace {
+    metaspace: MetaspacePool,
+    compressed_class_space: Option<MetaspacePool>,
+}
+
+impl Metaspace {
+    pub fn from_entries(entries: &HashMap<String, Entry>) -> Result<Self, Error> {
+        let lookup = Lookup::new(entries);
+        Ok(Self {
+            metaspace: MetaspacePool::read(&lookup, "sun.gc.metaspace")?,
+            compressed_class_space: optional(MetaspacePool::read(
+                &lookup,
+                "sun.gc.compressedclassspace",
+            ))?,
+        })
+    }
+
+    /// The whole metaspace, including the compressed class space.
+    pub fn metaspace(&self) -> &MetaspacePool {
+        &self.metaspace
+    }
+
+    /// The space of the class metadata, when class pointers are compressed.
+    pub fn compressed_class_space(&self) -> Option<&MetaspacePool> {
+        self.compressed_class_space.as_ref()
+    }
+}
+
+/// Sizes of the metaspace or of the compressed class space.
+#[derive(Debug, Clone)]
+pub struct MetaspacePool {
+    capacity: u64,
+    used: u64,
+    min_capacity: Option<u64>,
+    max_capacity: Option<u64>,
+}
+
+impl MetaspacePool {
+    fn read(lookup: &Lookup, prefix: &str) -> Result<Self, Error> {
+        let name = |field: &str| format!("{prefix}.{field}");
+        Ok(Self {
+            capacity: lookup.bytes(&name("capacity"))?,
+            used: lookup.bytes(&name("used"))?,
+            min_capacity: optional(lookup.bytes(&name("minCapacity")))?,
+            max_capacity: Self::limit(lookup, &name("maxCapacity"))?,
+        })
+    }
+
+    /// HotSpot publishes the max_uintx of an unset MaxMetaspaceSize, which reads as -1
+    /// once stored in a jlong, or as the largest jlong. Other negative values are refused.
+    fn limit(lookup: &Lookup, name: &str) -> Result<Option<u64>, Error> {
+        match lookup.long(name)? {
+            -1 | i64::MAX => Ok(None),
+            max_capacity => {
+                u64::try_from(max_capacity)
+                    .map(Some)
+                    .map_err(|_| Error::UnexpectedValue {
+                        name: name.to_string(),
+                    })
+            }
+        }
+    }
+
+    /// Bytes committed.
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    pub fn used(&self) -> u64 {
+        self.used
+    }
+
+    pub fn min_capacity(&self) -> Option<u64> {
+        self.min_capacity
+    }
+
+    /// Bytes that can be committed at most, None when unlimited.
+    pub fn max_capacity(&self) -> Option<u64> {
+        self.max_capacity
+    }
+
+    /// Whether the pool can grow without limit, as when MaxMetaspaceSize is unset.
+    pub fn is_unlimited(&self) -> bool {
+        self.max_capacity.is_none()
+    }
+
+    /// Percentage of the committed bytes used.
+    pub fn occupancy(&self) -> f64 {
+        percentage(self.used, self.capacity)
+    }
+
+    /// Percentage of the max capacity used, None when unlimited.
+    pub fn max_occupancy(&self) -> Option<f64> {
+        self.max_capacity
+            .map(|max_capacity| percentage(self.used, max_capacity))
+    }
+
+    /// Bytes that can still be used before an OutOfMemoryError, None when unlimited.
+    pub fn headroom(&self) -> Option<u64> {
+        self.max_capacity
+            .map(|max_capacity| max_capacity.saturating_sub(self.used))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parameterized::parameterized;
+
+    use crate::{EntryValue, Error, Metaspace, Unit, Variability, testing::FakeJvm};
+
+    const MIB: i64 = 1 << 20;
+
+    fn given_a_pool(
+        jvm: FakeJvm,
+        prefix: &str,
+        [capacity, used, max_capacity]: [i64; 3],
+    ) -> FakeJvm {
+        [
+            ("capacity", capacity),
+            ("used", used),
+            ("maxCapacity", max_capacity),
+            ("minCapacity", 0),
+        ]
+        .into_iter()
+        .fold(jvm, |jvm, (field, value)| {
+            jvm.entry(
+                &format!("{prefix}.{field}"),
+                EntryValue::Long(value),
+                Unit::Bytes,
+                Variability::Variable,
+            )
+        })
+    }
+
+    fn given_a_jvm(max_capacity: i64) -> FakeJvm {
+        let jvm = given_a_pool(
+            FakeJvm::new(),
+            "sun.gc.metaspace",
+            [64 * MIB, 48 * MIB, max_capacity],
+        );
+        given_a_pool(
+            jvm,
+            "sun.gc.compressedclassspace",
+            [8 * MIB, 6 * MIB, 1024 * MIB],
+        )
+    }
+
+    fn read(jvm: &FakeJvm) -> Result<Metaspace, Error> {
+        Metaspace::from_entries(jvm.snapshot().unwrap().entries())
+    }
+
+    #[test]
+    fn limited_pools_report_their_headroom() {
+        let metaspace = read(&given_a_jvm(256 * MIB)).unwrap();
+
+        let pool = metaspace.metaspace();
+        assert_eq!(75.0, pool.occupancy());
+        assert_eq!(Some(18.75), pool.max_occupancy());
+        assert_eq!(Some(208 * MIB as u64), pool.headroom());
+        assert_eq!(Some(0), pool.min_capacity());
+        assert!(!pool.is_unlimited());
+        assert_eq!(
+            Some(1018 * MIB as u64),
+            metaspace.compressed_class_space().unwrap().headroom()
+        );
+    }
+
+    #[parameterized(max_capacity = { -1, i64::MAX })]
+    fn unset_max_metaspace_sizes_are_unlimited(max_capacity: i64) {
+        let pool = read(&given_a_jvm(max_capacity))
+            .unwrap()
+            .metaspace()
+            .clone();
+
+        assert!(pool.is_unlimited());
+        assert_eq!(None, pool.headroom());
+        assert_eq!(None, pool.max_occupancy());
+        assert_eq!(75.0, pool.occupancy());
+    }
+
+    #[parameterized(max_capacity = { -2, i64::MIN })]
+    fn other_negative_max_capacities_are_refused(max_capacity: i64) {
+        assert!(matches!(
+            read(&given_a_jvm(max_capacity)),
+            Err(Error::UnexpectedValue { name }) if name == "sun.gc.metaspace.maxCapacity"
+        ));
+    }
+
+    #[test]
+    fn compressed_class_space_is_optional() {
+        let jvm = given_a_pool(FakeJvm::new(), "sun.gc.metaspace", [MIB, MIB, 2 * MIB]);
+
+        let metaspace = read(&jvm).unwrap();
+
+        assert!(metaspace.compressed_class_space().is_none());
+        assert!(matches!(
+            read(&FakeJvm::new()),
+            Err(Error::MissingEntry { .. })
+        ));
+    }
+}
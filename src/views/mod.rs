@@ -0,0 +1,32 @@
+/// Typed views over the entries published by the JVM subsystems, which work the same on the
+/// entries of a monitor and of a snapshot.
+mod class_loading_stats;
+mod compiler_stats;
+mod gc_stats;
+mod heap_layout;
+mod lookup;
+mod metaspace;
+mod safepoint_stats;
+mod startup_profile;
+mod thread_stats;
+mod tlab_stats;
+
+pub(crate) type ClassLoadingStats = class_loading_stats::ClassLoadingStats;
+pub(crate) type CompileType = compiler_stats::CompileType;
+pub(crate) type CompilerStats = compiler_stats::CompilerStats;
+pub(crate) type CompilerThreadStats = compiler_stats::CompilerThreadStats;
+pub(crate) type GcStats = gc_stats::GcStats;
+pub(crate) type CollectorStats = gc_stats::CollectorStats;
+pub(crate) type HeapLayout = heap_layout::HeapLayout;
+pub(crate) type Generation = heap_layout::Generation;
+pub(crate) type Space = heap_layout::Space;
+pub(crate) type Metaspace = metaspace::Metaspace;
+pub(crate) type MetaspacePool = metaspace::MetaspacePool;
+pub(crate) type SafepointStats = safepoint_stats::SafepointStats;
+pub(crate) type SafepointInterval = safepoint_stats::SafepointInterval;
+pub(crate) type StartupProfile = startup_profile::StartupProfile;
+pub(crate) type ThreadStats = thread_stats::ThreadStats;
+pub(crate) type TlabStats = tlab_stats::TlabStats;
+pub(crate) type Lookup<'a> = lookup::Lookup<'a>;
+
+pub(crate) use lookup::{optional, percentage};
@@ -0,0 +1,242 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::{
+    errors::Error,
+    jvm_clock,
+    jvm_monitor::Entry,
+    views::{Lookup, optional},
+};
+
+/// Safepoints of a JVM since its start, read from sun.rt.safepoint* and
+/// sun.rt.applicationTime.
+///
+/// The stop-the-world overhead over a period is the interval between the stats read at
+/// its start and at its end.
+#[derive(Debug, Clone)]
+pub struct SafepointStats {
+    safepoints: u64,
+    safepoint_time: Duration,
+    sync_time: Duration,
+    application_time: Duration,
+    uptime: Option<Duration>,
+}
+
+impl SafepointStats {
+    pub fn from_entries(entries: &HashMap<String, Entry>) -> Result<Self, Error> {
+        let lookup = Lookup::new(entries);
+        Ok(Self {
+            safepoints: lookup.count("sun.rt.safepoints")?,
+            safepoint_time: lookup.duration("sun.rt.safepointTime")?,
+            sync_time: lookup.duration("sun.rt.safepointSyncTime")?,
+            application_time: lookup.duration("sun.rt.applicationTime")?,
+            uptime: optional(lookup.duration(jvm_clock::TICKS))?,
+        })
+    }
+
+    /// Number of safepoints reached.
+    pub fn safepoints(&self) -> u64 {
+        self.safepoints
+    }
+
+    /// Time the application threads were stopped at safepoints.
+    pub fn safepoint_time(&self) -> Duration {
+        self.safepoint_time
+    }
+
+    /// Time spent bringing the threads to the safepoints.
+    pub fn sync_time(&self) -> Duration {
+        self.sync_time
+    }
+
+    /// Time the application threads ran between safepoints.
+    pub fn application_time(&self) -> Duration {
+        self.application_time
+    }
+
+    /// The safepoints reached since the earlier stats.
+    pub fn since(&self, earlier: &SafepointStats) -> SafepointInterval {
+        let application_time = self
+            .application_time
+            .saturating_sub(earlier.application_time);
+        let pause_time = self.safepoint_time.saturating_sub(earlier.safepoint_time);
+        let wall_time = match (self.uptime, earlier.uptime) {
+            (Some(uptime), Some(earlier)) => uptime.saturating_sub(earlier),
+            _ => application_time + pause_time,
+        };
+        SafepointInterval {
+            safepoints: self.safepoints.saturating_sub(earlier.safepoints),
+            pause_time,
+            sync_time: self.sync_time.saturating_sub(earlier.sync_time),
+            application_time,
+            wall_time,
+        }
+    }
+}
+
+/// Safepoints reached between two readings of the stats.
+#[derive(Debug, Clone)]
+pub struct SafepointInterval {
+    safepoints: u64,
+    pause_time: Duration,
+    sync_time: Duration,
+    application_time: Duration,
+    wall_time: Duration,
+}
+
+impl SafepointInterval {
+    pub fn safepoints(&self) -> u64 {
+        self.safepoints
+    }
+
+    /// Time the application threads were stopped.
+    pub fn pause_time(&self) -> Duration {
+        self.pause_time
+    }
+
+    /// Time spent bringing the threads to the safepoints.
+    pub fn sync_time(&self) -> Duration {
+        self.sync_time
+    }
+
+    pub fn application_time(&self) -> Duration {
+        self.application_time
+    }
+
+    /// Time elapsed between the readings, from sun.os.hrt.ticks when published, or else
+    /// the application and pause times.
+    pub fn wall_time(&self) -> Duration {
+        self.wall_time
+    }
+
+    /// Average time to reach a safepoint, None without safepoints.
+    pub fn average_time_to_safepoint(&self) -> Option<Duration> {
+        self.average(self.sync_time)
+    }
+
+    /// Average pause of a safepoint, None without safepoints.
+    pub fn average_pause(&self) -> Option<Duration> {
+        self.average(self.pause_time)
+    }
+
+    /// Fraction of the wall time the application was stopped, between 0 and 1.
+    pub fn stopped_fraction(&self) -> f64 {
+        if self.wall_time.is_zero() {
+            0.0
+        } else {
+            (self.pause_time.as_secs_f64() / self.wall_time.as_secs_f64()).min(1.0)
+        }
+    }
+
+    fn average(&self, total: Duration) -> Option<Duration> {
+        let nanos = total.as_nanos().checked_div(self.safepoints.into())?;
+        Some(Duration::from_nanos(nanos as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{EntryValue, Error, SafepointStats, Unit, Variability, testing::FakeJvm};
+
+    const SAFEPOINTS: &str = "sun.rt.safepoints";
+    const SAFEPOINT_TIME: &str = "sun.rt.safepointTime";
+    const SYNC_TIME: &str = "sun.rt.safepointSyncTime";
+    const APPLICATION_TIME: &str = "sun.rt.applicationTime";
+    const TICKS: &str = "sun.os.hrt.ticks";
+
+    fn given_a_jvm() -> FakeJvm {
+        [
+            (SAFEPOINTS, 100, Unit::Events),
+            (SAFEPOINT_TIME, 5_000, Unit::Ticks),
+            (SYNC_TIME, 500, Unit::Ticks),
+            (APPLICATION_TIME, 95_000, Unit::Ticks),
+            (TICKS, 100_000, Unit::Ticks),
+            ("sun.os.hrt.frequency", 1000, Unit::Hertz),
+        ]
+        .into_iter()
+        .fold(FakeJvm::new(), |jvm, (name, value, unit)| {
+            jvm.entry(name, EntryValue::Long(value), unit, Variability::Variable)
+        })
+    }
+
+    /// Runs for 10 seconds, 1 of which stopped at 4 safepoints.
+    fn run(jvm: &mut FakeJvm) {
+        jvm.add(SAFEPOINTS, 4).unwrap();
+        jvm.add(SAFEPOINT_TIME, 1_000).unwrap();
+        jvm.add(SYNC_TIME, 200).unwrap();
+        jvm.add(APPLICATION_TIME, 8_500).unwrap();
+        jvm.add(TICKS, 10_000).unwrap();
+    }
+
+    fn read(jvm: &FakeJvm) -> Result<SafepointStats, Error> {
+        SafepointStats::from_entries(jvm.snapshot().unwrap().entries())
+    }
+
+    #[test]
+    fn totals_are_converted_with_the_frequency() {
+        let stats = read(&given_a_jvm()).unwrap();
+
+        assert_eq!(100, stats.safepoints());
+        assert_eq!(Duration::from_secs(5), stats.safepoint_time());
+        assert_eq!(Duration::from_millis(500), stats.sync_time());
+        assert_eq!(Duration::from_secs(95), stats.application_time());
+    }
+
+    #[test]
+    fn intervals_break_down_the_pauses() {
+        let mut jvm = given_a_jvm();
+        let earlier = read(&jvm).unwrap();
+        run(&mut jvm);
+
+        let interval = read(&jvm).unwrap().since(&earlier);
+
+        assert_eq!(4, interval.safepoints());
+        assert_eq!(Duration::from_secs(1), interval.pause_time());
+        assert_eq!(Duration::from_secs(10), interval.wall_time());
+        assert_eq!(
+            Some(Duration::from_millis(50)),
+            interval.average_time_to_safepoint()
+        );
+        assert_eq!(Some(Duration::from_millis(250)), interval.average_pause());
+        assert_eq!(0.1, interval.stopped_fraction());
+    }
+
+    #[test]
+    fn averages_count_all_the_safepoints() {
+        let mut jvm = given_a_jvm();
+        let earlier = read(&jvm).unwrap();
+        jvm.add(SAFEPOINTS, 1 << 33).unwrap();
+        jvm.add(SAFEPOINT_TIME, 1 << 33).unwrap();
+
+        let interval = read(&jvm).unwrap().since(&earlier);
+
+        assert_eq!(Some(Duration::from_millis(1)), interval.average_pause());
+    }
+
+    #[test]
+    fn wall_time_falls_back_on_the_running_and_stopped_times() {
+        let mut jvm = given_a_jvm();
+        let earlier = read(&jvm).unwrap();
+        run(&mut jvm);
+        let later = SafepointStats::from_entries(
+            jvm.snapshot().unwrap().only(|name| name != TICKS).entries(),
+        )
+        .unwrap();
+
+        let interval = later.since(&earlier);
+
+        assert_eq!(Duration::from_millis(9_500), interval.wall_time());
+    }
+
+    #[test]
+    fn intervals_without_safepoints_have_no_averages() {
+        let jvm = given_a_jvm();
+        let stats = read(&jvm).unwrap();
+
+        let interval = stats.since(&stats);
+
+        assert_eq!(None, interval.average_time_to_safepoint());
+        assert_eq!(0.0, interval.stopped_fraction());
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    errors::Error,
+    jvm_clock,
+    jvm_monitor::Entry,
+    views::{Lookup, optional},
+};
+
+/// Startup phases of a JVM, read from sun.rt.createVmBeginTime, sun.rt.createVmEndTime,
+/// sun.rt.vmInitDoneTime and the class loading time.
+///
+/// HotSpot publishes the phases as milliseconds since the epoch, and 0 for the ones not
+/// reached yet.
+#[derive(Debug, Clone)]
+pub struct StartupProfile {
+    vm_creation_begin: SystemTime,
+    vm_creation_end: Option<SystemTime>,
+    vm_init_done: Option<SystemTime>,
+    class_loading_time: Option<Duration>,
+    uptime: Option<Duration>,
+}
+
+impl StartupProfile {
+    pub fn from_entries(entries: &HashMap<String, Entry>) -> Result<Self, Error> {
+        let lookup = Lookup::new(entries);
+        let time = |name: &str| -> Result<Option<SystemTime>, Error> {
+            Ok(match lookup.count(name)? {
+                0 => None,
+                millis => Some(SystemTime::UNIX_EPOCH + Duration::from_millis(millis)),
+            })
+        };
+        Ok(Self {
+            vm_creation_begin: time("sun.rt.createVmBeginTime")?.ok_or_else(|| {
+                Error::UnexpectedValue {
+                    name: "sun.rt.createVmBeginTime".to_string(),
+                }
+            })?,
+            vm_creation_end: time("sun.rt.createVmEndTime")?,
+            vm_init_done: time("sun.rt.vmInitDoneTime")?,
+            class_loading_time: optional(lookup.duration("sun.cls.time"))?,
+            uptime: optional(lookup.duration(jvm_clock::TICKS))?,
+        })
+    }
+
+    /// When the creation of the VM began.
+    pub fn vm_creation_begin(&self) -> SystemTime {
+        self.vm_creation_begin
+    }
+
+    /// When the VM was created, None while being created.
+    pub fn vm_creation_end(&self) -> Option<SystemTime> {
+        self.vm_creation_end
+    }
+
+    /// When the initialization of the VM was done, None while being initialized.
+    pub fn vm_init_done(&self) -> Option<SystemTime> {
+        self.vm_init_done
+    }
+
+    /// Time taken to create the VM.
+    pub fn vm_creation_time(&self) -> Option<Duration> {
+        self.since_begin(self.vm_creation_end?)
+    }
+
+    /// Time taken from the beginning of the creation of the VM to the end of its
+    /// initialization.
+    pub fn time_to_init_done(&self) -> Option<Duration> {
+        self.since_begin(self.vm_init_done?)
+    }
+
+    /// Time spent loading classes since the start, from sun.cls.time.
+    pub fn class_loading_time(&self) -> Option<Duration> {
+        self.class_loading_time
+    }
+
+    /// Fraction of the time to init done spent loading classes, between 0 and 1, None
+    /// while being initialized. Classes loaded after the initialization are counted too,
+    /// so that it is the share of the startup when read right after it.
+    pub fn class_loading_share(&self) -> Option<f64> {
+        let startup = self.time_to_init_done().filter(|time| !time.is_zero())?;
+        Some((self.class_loading_time?.as_secs_f64() / startup.as_secs_f64()).min(1.0))
+    }
+
+    /// Time since the start of the JVM, from sun.os.hrt.ticks.
+    pub fn uptime(&self) -> Option<Duration> {
+        self.uptime
+    }
+
+    fn since_begin(&self, time: SystemTime) -> Option<Duration> {
+        time.duration_since(self.vm_creation_begin).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use crate::{EntryValue, Error, StartupProfile, Unit, Variability, testing::FakeJvm};
+
+    const BEGIN: i64 = 1_700_000_000_000;
+
+    fn given_a_jvm(phases: [i64; 3]) -> FakeJvm {
+        let [begin, end, init_done] = phases;
+        [
+            (
+                "sun.rt.createVmBeginTime",
+                begin,
+                Unit::None,
+                Variability::Constant,
+            ),
+            (
+                "sun.rt.createVmEndTime",
+                end,
+                Unit::None,
+                Variability::Variable,
+            ),
+            (
+                "sun.rt.vmInitDoneTime",
+                init_done,
+                Unit::None,
+                Variability::Variable,
+            ),
+            ("sun.cls.time", 150, Unit::Ticks, Variability::Variable),
+            ("sun.os.hrt.ticks", 600, Unit::Ticks, Variability::Variable),
+            (
+                "sun.os.hrt.frequency",
+                1000,
+                Unit::Hertz,
+                Variability::Constant,
+            ),
+        ]
+        .into_iter()
+        .fold(FakeJvm::new(), |jvm, (name, value, unit, variability)| {
+            jvm.entry(name, EntryValue::Long(value), unit, variability)
+        })
+    }
+
+    fn read(jvm: &FakeJvm) -> Result<StartupProfile, Error> {
+        StartupProfile::from_entries(jvm.snapshot().unwrap().entries())
+    }
+
+    #[test]
+    fn phases_are_timed_from_the_creation_of_the_vm() {
+        let profile = read(&given_a_jvm([BEGIN, BEGIN + 80, BEGIN + 450])).unwrap();
+
+        assert_eq!(
+            SystemTime::UNIX_EPOCH + Duration::from_millis(BEGIN as u64),
+            profile.vm_creation_begin()
+        );
+        assert_eq!(Some(Duration::from_millis(80)), profile.vm_creation_time());
+        assert_eq!(
+            Some(Duration::from_millis(450)),
+            profile.time_to_init_done()
+        );
+    }
+
+    #[test]
+    fn class_loading_is_a_share_of_the_startup() {
+        let profile = read(&given_a_jvm([BEGIN, BEGIN + 80, BEGIN + 300])).unwrap();
+
+        assert_eq!(Some(Duration::from_millis(600)), profile.uptime());
+        assert_eq!(
+            Some(Duration::from_millis(150)),
+            profile.class_loading_time()
+        );
+        assert_eq!(Some(0.5), profile.class_loading_share());
+    }
+
+    #[test]
+    fn phases_not_reached_are_missing() {
+        let profile = read(&given_a_jvm([BEGIN, 0, 0])).unwrap();
+
+        assert_eq!(None, profile.vm_creation_end());
+        assert_eq!(None, profile.vm_creation_time());
+        assert_eq!(None, profile.time_to_init_done());
+        assert_eq!(None, profile.class_loading_share());
+    }
+
+    #[test]
+    fn vms_not_being_created_are_refused() {
+        assert!(matches!(
+            read(&given_a_jvm([0, 0, 0])),
+            Err(Error::UnexpectedValue { name }) if name == "sun.rt.createVmBeginTime"
+        ));
+        assert!(matches!(
+            read(&FakeJvm::new()),
+            Err(Error::MissingEntry { name }) if name == "sun.rt.createVmBeginTime"
+        ));
+    }
+}
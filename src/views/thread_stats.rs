@@ -0,0 +1,189 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::{
+    errors::Error,
+    jvm_clock,
+    jvm_monitor::Entry,
+    views::{Lookup, optional},
+};
+
+/// Threads of a JVM, read from java.threads.* and sun.threads.vmOperationTime.
+///
+/// Stats read at different times are compared to follow the creation of threads, e.g. to
+/// spot a leaking thread pool.
+#[derive(Debug, Clone)]
+pub struct ThreadStats {
+    live: u64,
+    live_peak: u64,
+    daemon: u64,
+    started: u64,
+    vm_operation_time: Option<Duration>,
+    uptime: Option<Duration>,
+}
+
+impl ThreadStats {
+    pub fn from_entries(entries: &HashMap<String, Entry>) -> Result<Self, Error> {
+        let lookup = Lookup::new(entries);
+        Ok(Self {
+            live: lookup.count("java.threads.live")?,
+            live_peak: lookup.count("java.threads.livePeak")?,
+            daemon: lookup.count("java.threads.daemon")?,
+            started: lookup.count("java.threads.started")?,
+            vm_operation_time: optional(lookup.duration("sun.threads.vmOperationTime"))?,
+            uptime: optional(lookup.duration(jvm_clock::TICKS))?,
+        })
+    }
+
+    /// Number of threads alive.
+    pub fn live(&self) -> u64 {
+        self.live
+    }
+
+    /// Highest number of threads alive at once.
+    pub fn live_peak(&self) -> u64 {
+        self.live_peak
+    }
+
+    /// Number of daemon threads alive.
+    pub fn daemon(&self) -> u64 {
+        self.daemon
+    }
+
+    /// Number of threads started since the start of the JVM.
+    pub fn started(&self) -> u64 {
+        self.started
+    }
+
+    /// Time spent by the VM thread in VM operations.
+    pub fn vm_operation_time(&self) -> Option<Duration> {
+        self.vm_operation_time
+    }
+
+    /// Time since the start of the JVM when the stats were read, from sun.os.hrt.ticks.
+    pub fn uptime(&self) -> Option<Duration> {
+        self.uptime
+    }
+
+    /// Threads started per second since the earlier stats, None when the time elapsed
+    /// is unknown or not positive.
+    pub fn creation_rate(&self, earlier: &ThreadStats) -> Option<f64> {
+        let elapsed = self.uptime?.checked_sub(earlier.uptime?)?;
+        if elapsed.is_zero() {
+            return None;
+        }
+        let started = self.started.saturating_sub(earlier.started);
+        Some(started as f64 / elapsed.as_secs_f64())
+    }
+
+    /// Whether the stats, in the order they were read, show threads piling up: the live
+    /// threads never decrease, end higher than they started and are at their peak.
+    pub fn unbounded_growth(samples: &[ThreadStats]) -> bool {
+        match (samples.first(), samples.last()) {
+            (Some(first), Some(last)) if samples.len() >= 3 => {
+                samples.windows(2).all(|pair| pair[0].live <= pair[1].live)
+                    && last.live > first.live
+                    && last.live >= last.live_peak
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{EntryValue, Error, ThreadStats, Unit, Variability, testing::FakeJvm};
+
+    const LIVE: &str = "java.threads.live";
+    const PEAK: &str = "java.threads.livePeak";
+    const STARTED: &str = "java.threads.started";
+
+    fn given_a_jvm() -> FakeJvm {
+        [
+            (LIVE, 10, Unit::None),
+            (PEAK, 10, Unit::None),
+            ("java.threads.daemon", 6, Unit::None),
+            (STARTED, 12, Unit::Events),
+            ("sun.threads.vmOperationTime", 2500, Unit::Ticks),
+            ("sun.os.hrt.ticks", 60_000, Unit::Ticks),
+            ("sun.os.hrt.frequency", 1000, Unit::Hertz),
+        ]
+        .into_iter()
+        .fold(FakeJvm::new(), |jvm, (name, value, unit)| {
+            jvm.entry(name, EntryValue::Long(value), unit, Variability::Variable)
+        })
+    }
+
+    /// Starts threads that never end, for a second.
+    fn leak(jvm: &mut FakeJvm, threads: i64) -> ThreadStats {
+        jvm.add(LIVE, threads).unwrap();
+        jvm.add(PEAK, threads).unwrap();
+        jvm.add(STARTED, threads).unwrap();
+        jvm.add("sun.os.hrt.ticks", 1000).unwrap();
+        read(jvm).unwrap()
+    }
+
+    fn read(jvm: &FakeJvm) -> Result<ThreadStats, Error> {
+        ThreadStats::from_entries(jvm.snapshot().unwrap().entries())
+    }
+
+    #[test]
+    fn counts_are_read() {
+        let stats = read(&given_a_jvm()).unwrap();
+
+        assert_eq!(10, stats.live());
+        assert_eq!(10, stats.live_peak());
+        assert_eq!(6, stats.daemon());
+        assert_eq!(12, stats.started());
+        assert_eq!(Some(Duration::from_millis(2500)), stats.vm_operation_time());
+        assert_eq!(Some(Duration::from_secs(60)), stats.uptime());
+    }
+
+    #[test]
+    fn creation_rate_is_per_second_between_samples() {
+        let mut jvm = given_a_jvm();
+        let earlier = read(&jvm).unwrap();
+        leak(&mut jvm, 10);
+        let later = leak(&mut jvm, 20);
+
+        assert_eq!(Some(15.0), later.creation_rate(&earlier));
+        assert_eq!(None, earlier.creation_rate(&later));
+        assert_eq!(None, later.creation_rate(&later));
+    }
+
+    #[test]
+    fn creation_rate_needs_the_clock() {
+        let stats = ThreadStats::from_entries(
+            given_a_jvm()
+                .snapshot()
+                .unwrap()
+                .only(|name| name.starts_with("java"))
+                .entries(),
+        )
+        .unwrap();
+
+        assert_eq!(None, stats.uptime());
+        assert_eq!(None, stats.creation_rate(&stats));
+    }
+
+    #[test]
+    fn leaking_threads_grow_without_bound() {
+        let mut jvm = given_a_jvm();
+        let samples = vec![read(&jvm).unwrap(), leak(&mut jvm, 5), leak(&mut jvm, 5)];
+
+        assert!(ThreadStats::unbounded_growth(&samples));
+        assert!(!ThreadStats::unbounded_growth(&samples[1..]));
+    }
+
+    #[test]
+    fn pools_that_shrink_back_are_bounded() {
+        let mut jvm = given_a_jvm();
+        let mut samples = vec![read(&jvm).unwrap(), leak(&mut jvm, 5)];
+        jvm.add(LIVE, -2).unwrap();
+        samples.push(read(&jvm).unwrap());
+
+        assert!(!ThreadStats::unbounded_growth(&samples));
+        assert!(!ThreadStats::unbounded_growth(&[]));
+    }
+}
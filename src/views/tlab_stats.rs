@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+
+use crate::{
+    errors::Error,
+    jvm_monitor::Entry,
+    views::{Lookup, optional, percentage},
+};
+
+/// Statistics of the thread-local allocation buffers of a JVM, read from sun.gc.tlab.*.
+///
+/// HotSpot publishes them for the interval between the last two collections: the counters
+/// are totals over all the threads, and their max* variants the largest value of a thread.
+#[derive(Debug, Clone)]
+pub struct TlabStats {
+    alloc_threads: u64,
+    fills: u64,
+    max_fills: u64,
+    alloc: u64,
+    gc_waste: u64,
+    max_gc_waste: u64,
+    refill_waste: u64,
+    max_refill_waste: u64,
+    fast_waste: Option<u64>,
+    max_fast_waste: Option<u64>,
+    slow_allocs: u64,
+    max_slow_allocs: u64,
+}
+
+impl TlabStats {
+    pub fn from_entries(entries: &HashMap<String, Entry>) -> Result<Self, Error> {
+        let lookup = Lookup::new(entries);
+        let name = |field: &str| format!("sun.gc.tlab.{field}");
+        Ok(Self {
+            alloc_threads: lookup.count(&name("allocThreads"))?,
+            fills: lookup.count(&name("fills"))?,
+            max_fills: lookup.count(&name("maxFills"))?,
+            alloc: lookup.bytes(&name("alloc"))?,
+            gc_waste: lookup.bytes(&name("gcWaste"))?,
+            max_gc_waste: lookup.bytes(&name("maxGcWaste"))?,
+            refill_waste: lookup.bytes(&name("refillWaste"))?,
+            max_refill_waste: lookup.bytes(&name("maxRefillWaste"))?,
+            fast_waste: optional(lookup.bytes(&name("fastWaste")))?,
+            max_fast_waste: optional(lookup.bytes(&name("maxFastWaste")))?,
+            slow_allocs: lookup.count(&name("slowAlloc"))?,
+            max_slow_allocs: lookup.count(&name("maxSlowAlloc"))?,
+        })
+    }
+
+    /// Number of threads that allocated.
+    pub fn alloc_threads(&self) -> u64 {
+        self.alloc_threads
+    }
+
+    /// Number of buffers handed out.
+    pub fn fills(&self) -> u64 {
+        self.fills
+    }
+
+    pub fn max_fills(&self) -> u64 {
+        self.max_fills
+    }
+
+    /// Bytes allocated in the buffers.
+    pub fn alloc(&self) -> u64 {
+        self.alloc
+    }
+
+    /// Bytes left unused in the buffers retired by the collection.
+    pub fn gc_waste(&self) -> u64 {
+        self.gc_waste
+    }
+
+    pub fn max_gc_waste(&self) -> u64 {
+        self.max_gc_waste
+    }
+
+    /// Bytes left unused in the buffers retired to get new ones.
+    pub fn refill_waste(&self) -> u64 {
+        self.refill_waste
+    }
+
+    pub fn max_refill_waste(&self) -> u64 {
+        self.max_refill_waste
+    }
+
+    /// Bytes wasted by the fast TLAB refills, only published by older JVMs.
+    pub fn fast_waste(&self) -> Option<u64> {
+        self.fast_waste
+    }
+
+    pub fn max_fast_waste(&self) -> Option<u64> {
+        self.max_fast_waste
+    }
+
+    /// Number of objects allocated outside of the buffers, as they did not fit.
+    pub fn slow_allocs(&self) -> u64 {
+        self.slow_allocs
+    }
+
+    pub fn max_slow_allocs(&self) -> u64 {
+        self.max_slow_allocs
+    }
+
+    /// Bytes wasted in all the ways, saturating at u64::MAX.
+    pub fn waste(&self) -> u64 {
+        self.gc_waste
+            .saturating_add(self.refill_waste)
+            .saturating_add(self.fast_waste.unwrap_or(0))
+    }
+
+    /// Percentage of the bytes allocated in the buffers that were wasted.
+    pub fn waste_percentage(&self) -> f64 {
+        percentage(self.waste(), self.alloc)
+    }
+
+    pub fn gc_waste_percentage(&self) -> f64 {
+        percentage(self.gc_waste, self.alloc)
+    }
+
+    pub fn refill_waste_percentage(&self) -> f64 {
+        percentage(self.refill_waste, self.alloc)
+    }
+
+    /// Share of the allocations made outside of the buffers, between 0 and 1, counting
+    /// each fill as an allocation in a buffer.
+    pub fn slow_alloc_ratio(&self) -> f64 {
+        match self.slow_allocs + self.fills {
+            0 => 0.0,
+            allocs => self.slow_allocs as f64 / allocs as f64,
+        }
+    }
+
+    /// Average bytes allocated in a buffer, 0 when none was handed out.
+    pub fn average_size(&self) -> u64 {
+        self.alloc.checked_div(self.fills).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EntryValue, Error, TlabStats, Unit, Variability, testing::FakeJvm};
+
+    const KIB: i64 = 1 << 10;
+
+    fn given_a_jvm(counters: &[(&str, i64)]) -> FakeJvm {
+        counters.iter().fold(FakeJvm::new(), |jvm, (field, value)| {
+            jvm.entry(
+                &format!("sun.gc.tlab.{field}"),
+                EntryValue::Long(*value),
+                Unit::Bytes,
+                Variability::Variable,
+            )
+        })
+    }
+
+    fn given_a_busy_jvm() -> FakeJvm {
+        given_a_jvm(&[
+            ("allocThreads", 4),
+            ("fills", 300),
+            ("maxFills", 200),
+            ("alloc", 9600 * KIB),
+            ("gcWaste", 96 * KIB),
+            ("maxGcWaste", 64 * KIB),
+            ("refillWaste", 192 * KIB),
+            ("maxRefillWaste", 128 * KIB),
+            ("slowAlloc", 100),
+            ("maxSlowAlloc", 90),
+        ])
+    }
+
+    fn read(jvm: &FakeJvm) -> Result<TlabStats, Error> {
+        TlabStats::from_entries(jvm.snapshot().unwrap().entries())
+    }
+
+    #[test]
+    fn waste_is_a_percentage_of_the_allocations() {
+        let stats = read(&given_a_busy_jvm()).unwrap();
+
+        assert_eq!(288 * KIB as u64, stats.waste());
+        assert_eq!(3.0, stats.waste_percentage());
+        assert_eq!(1.0, stats.gc_waste_percentage());
+        assert_eq!(2.0, stats.refill_waste_percentage());
+        assert_eq!(None, stats.fast_waste());
+    }
+
+    #[test]
+    fn averages_are_per_fill() {
+        let stats = read(&given_a_busy_jvm()).unwrap();
+
+        assert_eq!(32 * KIB as u64, stats.average_size());
+        assert_eq!(0.25, stats.slow_alloc_ratio());
+        assert_eq!(200, stats.max_fills());
+    }
+
+    #[test]
+    fn fast_waste_of_older_jvms_is_counted() {
+        let jvm = given_a_busy_jvm()
+            .entry(
+                "sun.gc.tlab.fastWaste",
+                EntryValue::Long(96 * KIB),
+                Unit::Bytes,
+                Variability::Variable,
+            )
+            .entry(
+                "sun.gc.tlab.maxFastWaste",
+                EntryValue::Long(KIB),
+                Unit::Bytes,
+                Variability::Variable,
+            );
+
+        let stats = read(&jvm).unwrap();
+
+        assert_eq!(4.0, stats.waste_percentage());
+        assert_eq!(Some(KIB as u64), stats.max_fast_waste());
+    }
+
+    #[test]
+    fn waste_saturates_instead_of_overflowing() {
+        let jvm = given_a_jvm(&[
+            ("allocThreads", 1),
+            ("fills", 1),
+            ("maxFills", 1),
+            ("alloc", KIB),
+            ("gcWaste", i64::MAX),
+            ("maxGcWaste", 0),
+            ("refillWaste", i64::MAX),
+            ("maxRefillWaste", 0),
+            ("fastWaste", i64::MAX),
+            ("slowAlloc", 0),
+            ("maxSlowAlloc", 0),
+        ]);
+
+        assert_eq!(u64::MAX, read(&jvm).unwrap().waste());
+    }
+
+    #[test]
+    fn intervals_without_allocations_have_no_waste() {
+        let jvm = given_a_jvm(&[
+            ("allocThreads", 0),
+            ("fills", 0),
+            ("maxFills", 0),
+            ("alloc", 0),
+            ("gcWaste", 0),
+            ("maxGcWaste", 0),
+            ("refillWaste", 0),
+            ("maxRefillWaste", 0),
+            ("slowAlloc", 0),
+            ("maxSlowAlloc", 0),
+        ]);
+
+        let stats = read(&jvm).unwrap();
+
+        assert_eq!(0.0, stats.waste_percentage());
+        assert_eq!(0.0, stats.slow_alloc_ratio());
+        assert_eq!(0, stats.average_size());
+        assert!(matches!(
+            read(&FakeJvm::new()),
+            Err(Error::MissingEntry { .. })
+        ));
+    }
+}